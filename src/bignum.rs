@@ -0,0 +1,290 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Mul, Shl, Shr, Sub};
+use std::str::FromStr;
+
+// An arbitrary precision unsigned integer, stored as little-endian 32 bit limbs with no trailing zero limbs
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct BigUint {
+    limbs: Vec<u32>,
+}
+
+impl BigUint {
+    pub fn zero() -> BigUint {
+        BigUint { limbs: Vec::new() }
+    }
+
+    fn from_limbs(mut limbs: Vec<u32>) -> BigUint {
+        while limbs.last() == Some(&0) {
+            limbs.pop();
+        }
+        BigUint { limbs }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    pub fn is_odd(&self) -> bool {
+        self.limbs.first().is_some_and(|limb| limb & 1 == 1)
+    }
+
+    pub fn bits(&self) -> usize {
+        match self.limbs.last() {
+            Some(top) => self.limbs.len() * 32 - top.leading_zeros() as usize,
+            None => 0,
+        }
+    }
+
+    pub fn checked_sub(&self, other: &BigUint) -> Option<BigUint> {
+        if *self < *other {
+            return None;
+        }
+        let mut limbs = Vec::with_capacity(self.limbs.len());
+        let mut borrow = 0_i64;
+        for (i, &limb) in self.limbs.iter().enumerate() {
+            let mut diff = limb as i64 - other.limbs.get(i).copied().unwrap_or(0) as i64 - borrow;
+            borrow = if diff < 0 { 1 } else { 0 };
+            if diff < 0 {
+                diff += 1 << 32;
+            }
+            limbs.push(diff as u32);
+        }
+        Some(BigUint::from_limbs(limbs))
+    }
+
+    // Divides in place by a small divisor, returning the remainder
+    fn div_rem_small(&mut self, divisor: u32) -> u32 {
+        let mut rem = 0_u64;
+        for limb in self.limbs.iter_mut().rev() {
+            let cur = (rem << 32) | *limb as u64;
+            *limb = (cur / divisor as u64) as u32;
+            rem = cur % divisor as u64;
+        }
+        *self = BigUint::from_limbs(std::mem::take(&mut self.limbs));
+        rem as u32
+    }
+
+    // The largest integer whose square is no more than self, found one bit at a time
+    pub fn isqrt(&self) -> BigUint {
+        if self.is_zero() {
+            return BigUint::zero();
+        }
+        let mut remainder = self.clone();
+        let mut result = BigUint::zero();
+        let mut bit = BigUint::from(1_u8) << ((self.bits() - 1) & !1);
+        while !bit.is_zero() {
+            let candidate = &result + &bit;
+            if remainder >= candidate {
+                remainder = remainder.checked_sub(&candidate).unwrap();
+                result = (result >> 1) + bit.clone();
+            } else {
+                result = result >> 1;
+            }
+            bit = bit >> 2;
+        }
+        result
+    }
+}
+
+impl From<u64> for BigUint {
+    fn from(n: u64) -> Self {
+        BigUint::from_limbs(vec![n as u32, (n >> 32) as u32])
+    }
+}
+
+impl From<u8> for BigUint {
+    fn from(n: u8) -> Self {
+        BigUint::from(n as u64)
+    }
+}
+
+impl FromStr for BigUint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err("Empty number".to_string());
+        }
+        let ten = BigUint::from(10_u8);
+        s.chars().try_fold(BigUint::zero(), |n, c| {
+            let digit = c.to_digit(10).ok_or_else(|| format!("Invalid digit '{}' in {}", c, s))?;
+            Ok(&n * &ten + BigUint::from(digit as u8))
+        })
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        let mut n = self.clone();
+        let mut chunks = Vec::new();
+        while !n.is_zero() {
+            chunks.push(n.div_rem_small(1_000_000_000));
+        }
+        write!(f, "{}", chunks.pop().unwrap())?;
+        for chunk in chunks.iter().rev() {
+            write!(f, "{:09}", chunk)?;
+        }
+        Ok(())
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.limbs.len().cmp(&other.limbs.len())
+            .then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Add for &BigUint {
+    type Output = BigUint;
+
+    fn add(self, other: &BigUint) -> BigUint {
+        let mut limbs = Vec::with_capacity(self.limbs.len().max(other.limbs.len()) + 1);
+        let mut carry = 0_u64;
+        for i in 0..self.limbs.len().max(other.limbs.len()) {
+            let sum = self.limbs.get(i).copied().unwrap_or(0) as u64
+                + other.limbs.get(i).copied().unwrap_or(0) as u64
+                + carry;
+            limbs.push(sum as u32);
+            carry = sum >> 32;
+        }
+        limbs.push(carry as u32);
+        BigUint::from_limbs(limbs)
+    }
+}
+
+impl Sub for &BigUint {
+    type Output = BigUint;
+
+    fn sub(self, other: &BigUint) -> BigUint {
+        self.checked_sub(other).expect("BigUint subtraction underflowed")
+    }
+}
+
+impl Mul for &BigUint {
+    type Output = BigUint;
+
+    fn mul(self, other: &BigUint) -> BigUint {
+        let mut limbs = vec![0_u32; self.limbs.len() + other.limbs.len()];
+        for (i, &a) in self.limbs.iter().enumerate() {
+            let mut carry = 0_u64;
+            for (j, &b) in other.limbs.iter().enumerate() {
+                let cur = limbs[i + j] as u64 + a as u64 * b as u64 + carry;
+                limbs[i + j] = cur as u32;
+                carry = cur >> 32;
+            }
+            limbs[i + other.limbs.len()] = carry as u32;
+        }
+        BigUint::from_limbs(limbs)
+    }
+}
+
+macro_rules! forward_owned_op {
+    ($op:ident, $method:ident) => {
+        impl $op for BigUint {
+            type Output = BigUint;
+
+            fn $method(self, other: BigUint) -> BigUint {
+                (&self).$method(&other)
+            }
+        }
+    };
+}
+
+forward_owned_op!(Add, add);
+forward_owned_op!(Sub, sub);
+forward_owned_op!(Mul, mul);
+
+impl Shl<usize> for BigUint {
+    type Output = BigUint;
+
+    fn shl(self, bits: usize) -> BigUint {
+        let (limb_shift, bit_shift) = (bits / 32, bits % 32);
+        let mut limbs = vec![0_u32; limb_shift];
+        let mut carry = 0_u32;
+        for &limb in &self.limbs {
+            if bit_shift == 0 {
+                limbs.push(limb);
+            } else {
+                limbs.push((limb << bit_shift) | carry);
+                carry = limb >> (32 - bit_shift);
+            }
+        }
+        limbs.push(carry);
+        BigUint::from_limbs(limbs)
+    }
+}
+
+impl Shr<usize> for BigUint {
+    type Output = BigUint;
+
+    fn shr(self, bits: usize) -> BigUint {
+        let (limb_shift, bit_shift) = (bits / 32, bits % 32);
+        if limb_shift >= self.limbs.len() {
+            return BigUint::zero();
+        }
+        let limbs = &self.limbs[limb_shift..];
+        let shifted = (0..limbs.len())
+            .map(|i| {
+                if bit_shift == 0 {
+                    limbs[i]
+                } else {
+                    (limbs[i] >> bit_shift) | (limbs.get(i + 1).copied().unwrap_or(0) << (32 - bit_shift))
+                }
+            })
+            .collect();
+        BigUint::from_limbs(shifted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(s: &str) -> BigUint {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_and_display() {
+        assert_eq!(big("0").to_string(), "0");
+        assert_eq!(big("000123").to_string(), "123");
+        let huge = "123456789012345678901234567890123456789012345678901234567890";
+        assert_eq!(big(huge).to_string(), huge);
+        assert!("12a".parse::<BigUint>().is_err());
+    }
+
+    #[test]
+    fn arithmetic() {
+        let a = big("340282366920938463463374607431768211456"); // 2^128
+        let b = big("18446744073709551616"); // 2^64
+        assert_eq!(&b * &b, a);
+        assert_eq!((&a + &b).to_string(), "340282366920938463481821351505477763072");
+        assert_eq!((&a - &b).to_string(), "340282366920938463444927863358058659840");
+        assert_eq!(b.checked_sub(&a), None);
+        assert_eq!(BigUint::from(1_u8) << 128, a);
+        assert_eq!(a.clone() >> 64, b);
+        assert!(a > b);
+    }
+
+    #[test]
+    fn integer_square_root() {
+        for n in 0..1000_u64 {
+            assert_eq!(BigUint::from(n).isqrt(), BigUint::from(n.isqrt()));
+        }
+        let root = big("123456789012345678901234567890");
+        let square = &root * &root;
+        assert_eq!(square.isqrt(), root);
+        assert_eq!((&square - &BigUint::from(1_u8)).isqrt(), &root - &BigUint::from(1_u8));
+    }
+}
@@ -1,3 +1,7 @@
+use std::collections::HashMap;
+
+use aoc23::gears::{Gear, GearRule};
+
 struct SchematicNumber {
     number: usize,
     row_index: usize,
@@ -12,6 +16,57 @@ struct Symbol {
 struct Schematic {
     numbers: Vec<SchematicNumber>,
     symbols: Vec<Symbol>,
+    index: SchematicIndex,
+}
+
+// Lookup from (row, col) of every occupied cell to the index of the number or symbol occupying it,
+// so adjacency queries only need to probe the handful of cells surrounding the item of interest.
+struct SchematicIndex {
+    number_cells: HashMap<(usize, usize), usize>,
+    symbol_cells: HashMap<(usize, usize), usize>,
+}
+
+impl SchematicIndex {
+    fn build(numbers: &[SchematicNumber], symbols: &[Symbol]) -> SchematicIndex {
+        let mut number_cells = HashMap::new();
+        for (index, number) in numbers.iter().enumerate() {
+            for col_index in number.start_col_index..=number.end_col_index {
+                number_cells.insert((number.row_index, col_index), index);
+            }
+        }
+        let symbol_cells = symbols.iter()
+            .enumerate()
+            .map(|(index, symbol)| ((symbol.row_index, symbol.col_index), index))
+            .collect();
+        SchematicIndex { number_cells, symbol_cells }
+    }
+
+    fn numbers_adjacent_to(&self, symbol: &Symbol) -> Vec<usize> {
+        let mut indexes = Vec::new();
+        for row_index in symbol.row_index.saturating_sub(1)..=symbol.row_index + 1 {
+            for col_index in symbol.col_index.saturating_sub(1)..=symbol.col_index + 1 {
+                if let Some(&index) = self.number_cells.get(&(row_index, col_index)) {
+                    // Cells of the same number are contiguous on a row, so a repeat can only be the previous cell
+                    if indexes.last() != Some(&index) {
+                        indexes.push(index);
+                    }
+                }
+            }
+        }
+        indexes
+    }
+
+    fn symbols_adjacent_to(&self, number: &SchematicNumber) -> Vec<usize> {
+        let mut indexes = Vec::new();
+        for row_index in number.row_index.saturating_sub(1)..=number.row_index + 1 {
+            for col_index in number.start_col_index.saturating_sub(1)..=number.end_col_index + 1 {
+                if let Some(&index) = self.symbol_cells.get(&(row_index, col_index)) {
+                    indexes.push(index);
+                }
+            }
+        }
+        indexes
+    }
 }
 
 impl Schematic {
@@ -60,45 +115,45 @@ impl Schematic {
                 current_number = None;
             }
         }
-        if current_number.is_some() {
+        if let Some(number) = current_number {
             // We've reached the end of a number and the end of the input
-            let number = current_number.unwrap();
             numbers.push(number);
         }
-        Schematic { numbers, symbols }
+        let index = SchematicIndex::build(&numbers, &symbols);
+        Schematic { numbers, symbols, index }
+    }
+
+    fn numbers_adjacent_to(&self, symbol: &Symbol) -> impl Iterator<Item=&SchematicNumber> {
+        self.index.numbers_adjacent_to(symbol).into_iter().map(|index| &self.numbers[index])
+    }
+
+    fn symbols_adjacent_to(&self, number: &SchematicNumber) -> impl Iterator<Item=&Symbol> {
+        self.index.symbols_adjacent_to(number).into_iter().map(|index| &self.symbols[index])
     }
 
     fn sum_part_numbers(&self) -> usize {
         self.numbers.iter()
-            .filter(|number| self.symbols.iter().any(|symbol| number.adjacent_to(symbol)))
+            .filter(|number| self.symbols_adjacent_to(number).next().is_some())
             .map(|number| number.number)
             .sum()
     }
 
-    fn sum_gear_ratios(&self) -> usize {
-        self.symbols.iter()
-            .filter(|symbol| symbol.symbol == '*')
+    fn gears(&self, rule: &GearRule) -> Vec<Gear> {
+        rule.find_gears(self.symbols.iter()
+            .filter(|symbol| rule.applies_to(symbol.symbol))
             .map(|symbol| {
-                self.numbers.iter()
-                    .filter(|number| number.adjacent_to(symbol))
+                let numbers = self.numbers_adjacent_to(symbol)
                     .map(|number| number.number)
-                    .collect::<Vec<_>>()
-                }
-            )
-            .filter(|numbers| numbers.len() == 2)
-            .map(|numbers| numbers[0] * numbers[1])
-            .sum()
+                    .collect::<Vec<_>>();
+                (symbol.symbol, (symbol.row_index, symbol.col_index), numbers)
+            }))
     }
-}
 
-impl SchematicNumber {
-    fn adjacent_to(&self, coord: &Symbol) -> bool {
-        coord.row_index >= self.row_index.saturating_sub(1)
-            && coord.row_index <= self.row_index.saturating_add(1)
-            && coord.col_index >= self.start_col_index.saturating_sub(1)
-            && coord.col_index <= self.end_col_index.saturating_add(1)
+    fn sum_gear_ratios(&self) -> usize {
+        self.gears(&GearRule::gear_ratio()).iter()
+            .map(|gear| gear.value)
+            .sum()
     }
-
 }
 
 fn main() {
@@ -113,6 +168,7 @@ fn main() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use aoc23::gears::{Combine, NeighbourCount};
 
     #[test]
     fn example_1() {
@@ -149,4 +205,57 @@ mod tests {
         let gear_ratios_sum = schematic.sum_gear_ratios();
         assert_eq!(gear_ratios_sum, 467835);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn adjacency_index() {
+        let input = "467..114..
+...*......
+..35..633.
+......#...
+617*......
+.....+.58.
+..592.....
+......755.
+...$.*....
+.664.598..
+";
+        let schematic = Schematic::parse(input);
+        let gear = schematic.symbols.iter().find(|s| s.row_index == 1 && s.col_index == 3).unwrap();
+        let neighbours = schematic.numbers_adjacent_to(gear).map(|n| n.number).collect::<Vec<_>>();
+        assert_eq!(neighbours, vec![467, 35]);
+
+        let isolated = schematic.numbers.iter().find(|n| n.number == 114).unwrap();
+        assert_eq!(schematic.symbols_adjacent_to(isolated).count(), 0);
+        let part = schematic.numbers.iter().find(|n| n.number == 617).unwrap();
+        let symbols = schematic.symbols_adjacent_to(part).map(|s| s.symbol).collect::<Vec<_>>();
+        assert_eq!(symbols, vec!['*']);
+    }
+
+    #[test]
+    fn gears_with_custom_rule() {
+        let input = "467..114..
+...*......
+..35..633.
+......#...
+617*......
+.....+.58.
+..592.....
+......755.
+...$.*....
+.664.598..
+";
+        let schematic = Schematic::parse(input);
+        let rule = GearRule::new("*#+$", NeighbourCount::AtLeast(1), Combine::Sum);
+        let gears = schematic.gears(&rule).iter()
+            .map(|gear| (gear.symbol, gear.row_index, gear.col_index, gear.value))
+            .collect::<Vec<_>>();
+        assert_eq!(gears, vec![
+            ('*', 1, 3, 502),
+            ('#', 3, 6, 633),
+            ('*', 4, 3, 617),
+            ('+', 5, 5, 592),
+            ('$', 8, 3, 664),
+            ('*', 8, 5, 1353),
+        ]);
+    }
+}
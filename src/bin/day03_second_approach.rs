@@ -1,61 +1,191 @@
-use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
 
-fn main() {
-    let input = include_bytes!("../../input/day03");
+use aoc23::gears::{Gear, GearRule};
 
-    let schematic = input.split(|b| *b == b'\n').collect::<Vec<_>>();
+#[derive(Debug, PartialEq, Eq)]
+enum SchematicEvent {
+    PartNumber(usize),
+    Gear(Gear),
+}
 
-    let mut current_number = None;
-    let mut numbers = Vec::new();
-    let mut next_id = 0_usize;
-    let mut asterisks = HashMap::new();
-    let mut sum_part_numbers = 0;
+// Streams a schematic from a reader, holding only the previous, current and next rows in memory. Each row is
+// processed once the row below it has been read, at which point everything adjacent to it is known.
+struct SchematicStream<R: BufRead> {
+    reader: R,
+    rule: GearRule,
+    prev: Vec<u8>,
+    cur: Vec<u8>,
+    next: Vec<u8>,
+    row_index: usize,
+    rows_remaining: usize,
+    events: VecDeque<SchematicEvent>,
+}
+
+impl<R: BufRead> SchematicStream<R> {
+    fn new(reader: R, rule: GearRule) -> SchematicStream<R> {
+        let mut stream = SchematicStream {
+            reader,
+            rule,
+            prev: Vec::new(),
+            cur: Vec::new(),
+            next: Vec::new(),
+            row_index: 0,
+            rows_remaining: 0,
+            events: VecDeque::new(),
+        };
+        stream.rows_remaining += stream.read_row_into_next();
+        std::mem::swap(&mut stream.cur, &mut stream.next);
+        stream.rows_remaining += stream.read_row_into_next();
+        stream
+    }
+
+    fn read_row_into_next(&mut self) -> usize {
+        self.next.clear();
+        let bytes_read = self.reader.read_until(b'\n', &mut self.next).unwrap();
+        while self.next.last().is_some_and(|b| *b == b'\n' || *b == b'\r') {
+            self.next.pop();
+        }
+        if bytes_read > 0 { 1 } else { 0 }
+    }
+
+    fn advance(&mut self) {
+        std::mem::swap(&mut self.prev, &mut self.cur);
+        std::mem::swap(&mut self.cur, &mut self.next);
+        self.row_index += 1;
+        self.rows_remaining -= 1;
+        self.rows_remaining += self.read_row_into_next();
+    }
+
+    fn window(&self) -> [&[u8]; 3] {
+        [&self.prev, &self.cur, &self.next]
+    }
 
-    let mut x = 0_usize;
-    let mut y = 0_usize;
-    for &byte in input {
-        if byte.is_ascii_digit() {
-            let digit = byte - b'0';
-            current_number = match current_number {
-                Some((start, n)) => Some((start, n * 10 + digit as usize)),
-                None => Some((x, digit as usize)),
-            };
-        } else if let Some((start, number)) = current_number {
-            let mut added_to_part_numbers = false;
-            for symbol_y in y.saturating_sub(1)..=y.saturating_add(1) {
-                for symbol_x in start.saturating_sub(1)..x.saturating_add(1) {
-                    let Some(&symbol) = schematic.get(symbol_y).and_then(|row| row.get(symbol_x)) else {
-                        continue;
-                    };
-                    if symbol != b'.' && !symbol.is_ascii_digit() {
-                        if !added_to_part_numbers {
-                            sum_part_numbers += number;
-                            added_to_part_numbers = true;
-                        }
-                        if symbol == b'*' {
-                            asterisks.entry((symbol_x, symbol_y)).or_insert_with(Vec::new).push(next_id);
-                        }
+    fn process_current_row(&mut self) {
+        let window = self.window();
+        let mut events = Vec::new();
+
+        let mut x = 0;
+        while x < self.cur.len() {
+            if !self.cur[x].is_ascii_digit() {
+                if is_symbol(self.cur[x]) && self.rule.applies_to(self.cur[x] as char) {
+                    let numbers = numbers_adjacent_to(&window, x);
+                    if let Some(gear) = self.rule.apply(self.cur[x] as char, self.row_index, x, numbers) {
+                        events.push(SchematicEvent::Gear(gear));
                     }
                 }
+                x += 1;
+                continue;
+            }
+            let (start, end, number) = read_number(&self.cur, x);
+            let is_part_number = window.iter().any(|row| {
+                (start.saturating_sub(1)..=end + 1).any(|col| row.get(col).is_some_and(|b| is_symbol(*b)))
+            });
+            if is_part_number {
+                events.push(SchematicEvent::PartNumber(number));
             }
+            x = end + 1;
+        }
+
+        self.events.extend(events);
+    }
+}
+
+impl<R: BufRead> Iterator for SchematicStream<R> {
+    type Item = SchematicEvent;
 
-            numbers.push(number);
-            next_id += 1;
-            current_number = None;
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.events.is_empty() {
+            if self.rows_remaining == 0 {
+                return None;
+            }
+            self.process_current_row();
+            self.advance();
         }
-        if byte == b'\n' {
-            y += 1;
-            x = 0;
-        } else {
-            x += 1;
+        self.events.pop_front()
+    }
+}
+
+fn is_symbol(byte: u8) -> bool {
+    byte != b'.' && !byte.is_ascii_digit()
+}
+
+// Returns the (start, end) columns (inclusive) and value of the number with a digit at col
+fn read_number(row: &[u8], col: usize) -> (usize, usize, usize) {
+    let mut start = col;
+    while start > 0 && row[start - 1].is_ascii_digit() {
+        start -= 1;
+    }
+    let mut end = col;
+    while end + 1 < row.len() && row[end + 1].is_ascii_digit() {
+        end += 1;
+    }
+    let number = row[start..=end].iter().fold(0, |n, b| n * 10 + (b - b'0') as usize);
+    (start, end, number)
+}
+
+fn numbers_adjacent_to(window: &[&[u8]; 3], col: usize) -> Vec<usize> {
+    let mut numbers = Vec::new();
+    for row in window {
+        for x in col.saturating_sub(1)..=col + 1 {
+            if !row.get(x).is_some_and(|b| b.is_ascii_digit()) {
+                continue;
+            }
+            // Only count each number once, from the leftmost of its digits that touches the symbol
+            if x > col.saturating_sub(1) && row[x - 1].is_ascii_digit() {
+                continue;
+            }
+            numbers.push(read_number(row, x).2);
         }
     }
+    numbers
+}
 
-    let sum_gear_ratios: usize = asterisks.iter()
-        .filter(|(_, ids)| ids.len() == 2)
-        .map(|(_, ids)| ids.iter().map(|id| numbers[*id]).product::<usize>())
-        .sum();
+fn main() {
+    let stream: Box<dyn BufRead> = match std::env::args().nth(1) {
+        Some(path) => Box::new(BufReader::new(File::open(path).unwrap())),
+        None => Box::new(&include_bytes!("../../input/day03")[..]),
+    };
+
+    let mut sum_part_numbers = 0;
+    let mut sum_gear_ratios = 0;
+    for event in SchematicStream::new(stream, GearRule::gear_ratio()) {
+        match event {
+            SchematicEvent::PartNumber(number) => sum_part_numbers += number,
+            SchematicEvent::Gear(gear) => sum_gear_ratios += gear.value,
+        }
+    }
 
     println!("Part 1: {}", sum_part_numbers);
     println!("Part 2: {}", sum_gear_ratios);
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn example() {
+        let input = "467..114..
+...*......
+..35..633.
+......#...
+617*......
+.....+.58.
+..592.....
+......755.
+...$.*....
+.664.598..
+";
+        let events = SchematicStream::new(input.as_bytes(), GearRule::gear_ratio()).collect::<Vec<_>>();
+        let part_numbers = events.iter()
+            .filter_map(|e| match e { SchematicEvent::PartNumber(n) => Some(*n), _ => None })
+            .collect::<Vec<_>>();
+        assert_eq!(part_numbers, vec![467, 35, 633, 617, 592, 755, 664, 598]);
+        let gears = events.iter()
+            .filter_map(|e| match e { SchematicEvent::Gear(g) => Some((g.row_index, g.col_index, g.value)), _ => None })
+            .collect::<Vec<_>>();
+        assert_eq!(gears, vec![(1, 3, 16345), (8, 5, 451490)]);
+    }
+}
@@ -1,30 +1,196 @@
 use std::collections::HashSet;
 
+struct CardReport {
+    id: usize,
+    matching_numbers: Vec<usize>,
+    points: usize,
+    copies: usize,
+    // (id of earlier card, copies of this card it won) - the remaining copy is the original card
+    contributions: Vec<(usize, usize)>,
+}
+
+impl CardReport {
+    fn explain(&self) -> String {
+        let mut lines = vec![
+            format!("Card {}", self.id),
+            format!("  Matching numbers: {:?}", self.matching_numbers),
+            format!("  Points: {}", self.points),
+            format!("  Copies: {} (1 original)", self.copies),
+        ];
+        for (from_id, copies) in &self.contributions {
+            lines.push(format!("    +{} from card {}", copies, from_id));
+        }
+        lines.join("\n")
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ParseError {
+    MissingCardId(usize),
+    InvalidCardId(usize, String),
+    MismatchedCardId { line: usize, expected: usize, found: usize },
+    MissingSeparator(usize),
+    InvalidNumber(usize, String),
+    DuplicateNumber(usize, usize),
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ParseError::MissingCardId(line) => write!(f, "line {}: expected 'Card <id>:'", line),
+            ParseError::InvalidCardId(line, id) => write!(f, "line {}: invalid card id '{}'", line, id),
+            ParseError::MismatchedCardId { line, expected, found } =>
+                write!(f, "line {}: expected card {} but found card {}", line, expected, found),
+            ParseError::MissingSeparator(line) => write!(f, "line {}: missing '|' between number lists", line),
+            ParseError::InvalidNumber(line, n) => write!(f, "line {}: invalid number '{}'", line, n),
+            ParseError::DuplicateNumber(line, n) => write!(f, "line {}: number {} appears more than once", line, n),
+        }
+    }
+}
+
+struct Scratchcard {
+    id: usize,
+    winning: Vec<usize>,
+    held: HashSet<usize>,
+}
+
+impl Scratchcard {
+    // line_number is 1-based and only used for error reporting
+    fn parse(line: &str, line_number: usize) -> Result<Scratchcard, ParseError> {
+        let (card, all_nums) = line.split_once(':').ok_or(ParseError::MissingCardId(line_number))?;
+        let id = card.strip_prefix("Card").ok_or(ParseError::MissingCardId(line_number))?.trim();
+        let id = id.parse::<usize>().map_err(|_| ParseError::InvalidCardId(line_number, id.to_string()))?;
+        let (winning, held) = all_nums.split_once('|').ok_or(ParseError::MissingSeparator(line_number))?;
+        let winning = Scratchcard::parse_numbers(winning, line_number)?;
+        let held = Scratchcard::parse_numbers(held, line_number)?.into_iter().collect();
+        Ok(Scratchcard { id, winning, held })
+    }
+
+    fn parse_numbers(numbers: &str, line_number: usize) -> Result<Vec<usize>, ParseError> {
+        let mut seen = HashSet::new();
+        numbers.split_whitespace()
+            .map(|n| {
+                let n = n.parse::<usize>().map_err(|_| ParseError::InvalidNumber(line_number, n.to_string()))?;
+                if !seen.insert(n) {
+                    return Err(ParseError::DuplicateNumber(line_number, n));
+                }
+                Ok(n)
+            })
+            .collect()
+    }
+
+    fn matching_numbers(&self) -> Vec<usize> {
+        self.winning.iter().copied().filter(|n| self.held.contains(n)).collect()
+    }
+
+    fn points(&self) -> usize {
+        let count_of_winning_nums = self.matching_numbers().len();
+        if count_of_winning_nums > 0 { 2_usize.pow(count_of_winning_nums as u32 - 1) } else { 0 }
+    }
+}
+
+fn parse(input: &str) -> Result<Vec<Scratchcard>, ParseError> {
+    input.lines()
+        .enumerate()
+        .map(|(index, line)| {
+            let card = Scratchcard::parse(line, index + 1)?;
+            if card.id != index + 1 {
+                return Err(ParseError::MismatchedCardId { line: index + 1, expected: index + 1, found: card.id });
+            }
+            Ok(card)
+        })
+        .collect()
+}
+
+fn analyse(cards: &[Scratchcard]) -> Vec<CardReport> {
+    let mut reports = cards.iter()
+        .map(|card| CardReport {
+            id: card.id,
+            matching_numbers: card.matching_numbers(),
+            points: card.points(),
+            copies: 1,
+            contributions: Vec::new(),
+        })
+        .collect::<Vec<_>>();
+
+    // For the N next cards, where N is the count of winning numbers, increase the number of cards by the
+    // number of copies of the current card
+    for index in 0..reports.len() {
+        let (id, copies, count_of_winning_nums) = (reports[index].id, reports[index].copies, reports[index].matching_numbers.len());
+        for report in reports.iter_mut().skip(index + 1).take(count_of_winning_nums) {
+            report.copies += copies;
+            report.contributions.push((id, copies));
+        }
+    }
+
+    reports
+}
+
+fn part1(cards: &[Scratchcard]) -> usize {
+    cards.iter().map(|card| card.points()).sum()
+}
+
+fn part2(cards: &[Scratchcard]) -> usize {
+    analyse(cards).iter().map(|report| report.copies).sum()
+}
+
 fn main() {
     let input = include_str!("../../input/day04");
+    let cards = parse(input).unwrap_or_else(|e| panic!("Invalid scratchcards: {}", e));
+
+    let args = std::env::args().collect::<Vec<_>>();
+    if let Some(pos) = args.iter().position(|arg| arg == "--explain") {
+        let id = args.get(pos + 1).and_then(|id| id.parse::<usize>().ok()).expect("--explain requires a card number");
+        let reports = analyse(&cards);
+        let report = reports.iter().find(|report| report.id == id).unwrap_or_else(|| panic!("No card {}", id));
+        println!("{}", report.explain());
+        return;
+    }
 
-    let mut sum_of_points = 0_usize;
-    let mut num_of_copies = input.lines().map(|_| 1_usize).collect::<Vec<_>>();
-    for (index, line) in input.lines().enumerate() {
-        let (_, all_nums) = line.split_once(": ").unwrap();
-        let (winning, actual) = all_nums.split_once(" | ").unwrap();
-        let winning = winning.split_whitespace().map(|n| n.parse::<usize>().unwrap()).collect::<Vec<_>>();
-        let actual = actual.split_whitespace().map(|n| n.parse::<usize>().unwrap()).collect::<HashSet<_>>();
+    println!("Part 1: {}", part1(&cards));
+    println!("Part 2: {}", part2(&cards));
+}
 
-        let count_of_winning_nums = winning.iter().filter(|n| actual.contains(n)).count();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        let points_for_card = if count_of_winning_nums > 0 { 2_usize.pow(count_of_winning_nums as u32 - 1) } else { 0 };
-        sum_of_points += points_for_card;
+    const EXAMPLE: &str = "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
+Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19
+Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1
+Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83
+Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36
+Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11
+";
 
-        // For the N next cards, where N is the count of winning numbers, increase the number of cards by the
-        // number of copies of the current card
-        for i in index + 1..(index + 1 + count_of_winning_nums).min(num_of_copies.len()) {
-            num_of_copies[i] += num_of_copies[index];
-        }
+    #[test]
+    fn example_1() {
+        let cards = parse(EXAMPLE).unwrap();
+        assert_eq!(part1(&cards), 13);
     }
 
-    println!("Part 1: {}", sum_of_points);
+    #[test]
+    fn example_2() {
+        let cards = parse(EXAMPLE).unwrap();
+        assert_eq!(part2(&cards), 30);
+    }
+
+    #[test]
+    fn example_ancestry() {
+        let reports = analyse(&parse(EXAMPLE).unwrap());
+        assert_eq!(reports[0].matching_numbers, vec![48, 83, 86, 17]);
+        assert_eq!(reports[3].copies, 8);
+        assert_eq!(reports[3].contributions, vec![(1, 1), (2, 2), (3, 4)]);
+        assert_eq!(reports[5].contributions, vec![]);
+    }
 
-    let total_cards: usize = num_of_copies.iter().sum();
-    println!("Part 2: {}", total_cards);
-}
\ No newline at end of file
+    #[test]
+    fn parse_errors() {
+        assert_eq!(parse("Card 1: 41 48 83 86 17 83 86  6").err(), Some(ParseError::MissingSeparator(1)));
+        assert_eq!(parse("Card 1: 41 48 41 | 83 86").err(), Some(ParseError::DuplicateNumber(1, 41)));
+        assert_eq!(parse("Card 1: 41 48 | 83 83").err(), Some(ParseError::DuplicateNumber(1, 83)));
+        assert_eq!(parse("Card 1: 41 | 83\nCard 3: 41 | 83").err(), Some(ParseError::MismatchedCardId { line: 2, expected: 2, found: 3 }));
+        assert_eq!(parse("41 48 | 83 86").err(), Some(ParseError::MissingCardId(1)));
+        assert_eq!(parse("Card 1: 41 4x | 83 86").err(), Some(ParseError::InvalidNumber(1, "4x".to_string())));
+    }
+}
@@ -1,6 +1,8 @@
 #![feature(iter_array_chunks)]
 
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+
+use aoc23::intervals::{Interval, IntervalMap, IntervalSet};
 
 struct MapRange {
     source_start: usize,
@@ -8,138 +10,221 @@ struct MapRange {
     length: usize,
 }
 
-#[derive(Eq, PartialEq, PartialOrd, Ord, Hash, Clone, Debug)]
-enum Category {
-    Seed,
-    Soil,
-    Fertilizer,
-    Water,
-    Light,
-    Temperature,
-    Humidity,
-    Location,
+struct CategoryMap {
+    dest: String,
+    map_ranges: Vec<MapRange>,
 }
 
-use Category::*;
-const CATEGORY_PAIRS: [(Category, Category); 7] = [
-    (Seed, Soil),
-    (Soil, Fertilizer),
-    (Fertilizer, Water),
-    (Water, Light),
-    (Light, Temperature),
-    (Temperature, Humidity),
-    (Humidity, Location),
-];
-
-fn parse(input: &str) -> (Vec<usize>, HashMap<Category, Vec<MapRange>>) {
-    let mut lines = input.lines();
-
-    let start_seeds = lines
-        .next()
-        .unwrap()
-        .split_once(": ")
-        .unwrap()
-        .1
-        .split_ascii_whitespace()
-        .map(|s| s.parse::<usize>().unwrap())
-        .collect::<Vec<_>>();
+struct Almanac {
+    seeds: Vec<usize>,
+    // Source category name -> maps from that category to others
+    maps_by_source: HashMap<String, Vec<CategoryMap>>,
+}
 
-    // Consume empty line, and first section title
-    lines.next();
-    lines.next();
+impl Almanac {
+    fn parse(input: &str) -> Almanac {
+        let mut sections = input.split("\n\n");
 
-    let mut mappings_by_category = HashMap::new();
+        let seeds = sections
+            .next()
+            .unwrap()
+            .split_once(": ")
+            .unwrap()
+            .1
+            .split_ascii_whitespace()
+            .map(|s| s.parse::<usize>().unwrap())
+            .collect::<Vec<_>>();
 
-    for (source, _dest) in &CATEGORY_PAIRS {
-        let mut category_maps = Vec::new();
-        while let Some(line) = lines.next() {
-            if line.is_empty() {
-                // Consume next section title
-                lines.next();
-                break;
-            }
-            let mut nums = line
-                .split_ascii_whitespace()
-                .map(|s| s.parse::<usize>().unwrap());
-            let dest_start = nums.next().unwrap();
-            let source_start = nums.next().unwrap();
-            let length = nums.next().unwrap();
-            category_maps.push(MapRange {
-                source_start,
-                dest_start,
-                length,
+        let mut maps_by_source = HashMap::new();
+        for section in sections {
+            let mut lines = section.lines();
+            let title = lines.next().unwrap();
+            let (source, dest) = title
+                .strip_suffix(" map:")
+                .and_then(|t| t.split_once("-to-"))
+                .unwrap_or_else(|| panic!("Invalid section title: {}", title));
+
+            let map_ranges = lines
+                .map(|line| {
+                    let mut nums = line
+                        .split_ascii_whitespace()
+                        .map(|s| s.parse::<usize>().unwrap());
+                    let dest_start = nums.next().unwrap();
+                    let source_start = nums.next().unwrap();
+                    let length = nums.next().unwrap();
+                    MapRange {
+                        source_start,
+                        dest_start,
+                        length,
+                    }
+                })
+                .collect();
+
+            maps_by_source.entry(source.to_string()).or_insert_with(Vec::new).push(CategoryMap {
+                dest: dest.to_string(),
+                map_ranges,
             });
         }
-        mappings_by_category.insert(source.clone(), category_maps);
+
+        Almanac { seeds, maps_by_source }
+    }
+
+    // The maps to apply, in order, to get from one category to another (found by breadth first search)
+    fn path(&self, from: &str, to: &str) -> Option<Vec<&[MapRange]>> {
+        let mut previous: HashMap<&str, (&str, &[MapRange])> = HashMap::new();
+        let mut queue = VecDeque::from([from]);
+        while let Some(category) = queue.pop_front() {
+            if category == to {
+                let mut path = Vec::new();
+                let mut cur = to;
+                while cur != from {
+                    let (prev, map_ranges) = previous[cur];
+                    path.push(map_ranges);
+                    cur = prev;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for category_map in self.maps_by_source.get(category).into_iter().flatten() {
+                if category_map.dest != from && !previous.contains_key(category_map.dest.as_str()) {
+                    previous.insert(&category_map.dest, (category, &category_map.map_ranges));
+                    queue.push_back(&category_map.dest);
+                }
+            }
+        }
+        None
     }
 
-    (start_seeds, mappings_by_category)
+    // A single map between any two connected categories. If the maps only lead from `to` to `from`, the
+    // combined map is inverted instead.
+    fn mapping(&self, from: &str, to: &str) -> Option<IntervalMap<usize>> {
+        let compose_path = |path: Vec<&[MapRange]>| path.into_iter().fold(IntervalMap::new(), |composed, map_ranges| {
+            composed.compose(&to_interval_map(map_ranges))
+        });
+        match self.path(from, to) {
+            Some(path) => Some(compose_path(path)),
+            None => compose_path(self.path(to, from)?).invert(),
+        }
+    }
+}
+
+fn to_interval_map(map_ranges: &[MapRange]) -> IntervalMap<usize> {
+    let mut map = IntervalMap::new();
+    for mr in map_ranges {
+        map.insert(Interval::new(mr.source_start, mr.source_start + mr.length), mr.dest_start);
+    }
+    map
+}
+
+// The lowest location reachable from any of the seed ranges, and the seed that reaches it
+fn lowest_location_seed(
+    seed_ranges: &IntervalSet<usize>,
+    seed_to_location: &IntervalMap<usize>,
+    location_to_seed: &IntervalMap<usize>,
+) -> (usize, usize) {
+    let location = seed_to_location.map_set(seed_ranges).first().unwrap().start;
+    (location_to_seed.get(location), location)
 }
 
 fn part1(input: &str) -> usize {
-    let (start_seeds, mappings_by_category) = parse(input);
+    let almanac = Almanac::parse(input);
+    let seed_to_location = almanac.mapping("seed", "location").unwrap();
 
-    start_seeds
+    almanac.seeds
         .iter()
-        .map(|seed| {
-            let mut source_id = *seed;
-            for (source, _dest) in &CATEGORY_PAIRS {
-                let map_ranges = mappings_by_category.get(source).unwrap();
-                let dest_id = map_ranges
-                    .iter()
-                    .find(|mr| {
-                        mr.source_start <= source_id && source_id < mr.source_start + mr.length
-                    })
-                    .map(|mr| mr.dest_start + source_id - mr.source_start)
-                    .unwrap_or(source_id);
-                source_id = dest_id;
-            }
-            source_id
-        })
+        .map(|seed| seed_to_location.get(*seed))
         .min()
         .unwrap()
 }
 
-fn part2(input: &str) -> usize {
-    let (start_seeds, mappings_by_category) = parse(input);
-
-    let start_seed_ranges = start_seeds.into_iter().array_chunks::<2>().collect::<Vec<_>>();
-
-    start_seed_ranges.into_iter()
-        .flat_map(|[seed_range_start, seed_range_length]| {
-            (seed_range_start..seed_range_start + seed_range_length)
-                .map(|seed| {
-                    let mut source_id = seed;
-                    for (source, _dest) in &CATEGORY_PAIRS {
-                        let map_ranges = mappings_by_category.get(source).unwrap();
-                        let dest_id = map_ranges
-                            .iter()
-                            .find(|mr| {
-                                mr.source_start <= source_id && source_id < mr.source_start + mr.length
-                            })
-                            .map(|mr| mr.dest_start + source_id - mr.source_start)
-                            .unwrap_or(source_id);
-                        source_id = dest_id;
+// How a range of seeds reached a range of locations: the seed range from the input it came from, and the
+// map range applied at each step (None where the values were passed through unchanged)
+struct Provenance<'a> {
+    seed_range: Interval<usize>,
+    seeds: Interval<usize>,
+    locations: Interval<usize>,
+    map_ranges: Vec<Option<&'a MapRange>>,
+}
+
+fn propagate<'a>(seed_ranges: &[Interval<usize>], path: &[&'a [MapRange]]) -> Vec<Provenance<'a>> {
+    let mut provenances = seed_ranges.iter()
+        .map(|&seed_range| Provenance { seed_range, seeds: seed_range, locations: seed_range, map_ranges: Vec::new() })
+        .collect::<Vec<_>>();
+
+    for map_ranges in path {
+        let map = to_interval_map(map_ranges);
+        provenances = provenances.into_iter()
+            .flat_map(|provenance| {
+                map.segments(provenance.locations).into_iter().map(move |(segment, dest_start)| {
+                    let seeds_start = provenance.seeds.start + (segment.start - provenance.locations.start);
+                    let map_range = map_ranges.iter().find(|mr| {
+                        mr.source_start <= segment.start && segment.start < mr.source_start + mr.length
+                    });
+                    let mut chain = provenance.map_ranges.clone();
+                    chain.push(map_range);
+                    Provenance {
+                        seed_range: provenance.seed_range,
+                        seeds: Interval::new(seeds_start, seeds_start + segment.len()),
+                        locations: Interval::new(dest_start, dest_start + segment.len()),
+                        map_ranges: chain,
                     }
-                    source_id
                 })
-        })
-        .min()
+            })
+            .collect();
+    }
+
+    provenances
+}
+
+fn nearest_location_for_seed_ranges(almanac: &Almanac) -> Provenance<'_> {
+    let path = almanac.path("seed", "location").unwrap();
+
+    let seed_ranges = almanac.seeds.iter().copied()
+        .array_chunks::<2>()
+        .map(|[start, length]| Interval::new(start, start + length))
+        .collect::<Vec<_>>();
+
+    propagate(&seed_ranges, &path).into_iter()
+        .min_by_key(|provenance| provenance.locations.start)
         .unwrap()
 }
 
+fn part2(input: &str) -> usize {
+    let almanac = Almanac::parse(input);
+    nearest_location_for_seed_ranges(&almanac).locations.start
+}
+
 fn main() {
     let input = include_str!("../../input/day05");
 
     let nearest_location = part1(input);
     println!("Part 1: {}", nearest_location);
 
+    let almanac = Almanac::parse(input);
+    let seed_to_location = almanac.mapping("seed", "location").unwrap();
+    let location_to_seed = almanac.mapping("location", "seed").expect("Almanac maps are not one-to-one");
+    let seeds = almanac.seeds.iter().map(|&seed| Interval::new(seed, seed + 1)).collect();
+    let (seed, _) = lowest_location_seed(&seeds, &seed_to_location, &location_to_seed);
+    println!("  Reached from seed {}", seed);
+
     let nearest_location_2 = part2(input);
     println!("Part 2: {}", nearest_location_2);
+
+    let provenance = nearest_location_for_seed_ranges(&almanac);
+    println!("  Reached from seed {} in seed range {}..={}",
+        provenance.seeds.start, provenance.seed_range.start, provenance.seed_range.last());
+    for map_range in &provenance.map_ranges {
+        match map_range {
+            Some(mr) => println!("  via map range {} {} {}", mr.dest_start, mr.source_start, mr.length),
+            None => println!("  via no map range"),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     const EXAMPLE: &str = "seeds: 79 14 55 13
 
 seed-to-soil map:
@@ -176,11 +261,80 @@ humidity-to-location map:
 
     #[test]
     fn example_part1() {
-        assert_eq!(super::part1(EXAMPLE), 35);
+        assert_eq!(part1(EXAMPLE), 35);
     }
 
     #[test]
     fn example_part2() {
-        assert_eq!(super::part2(EXAMPLE), 46);
+        assert_eq!(part2(EXAMPLE), 46);
+    }
+
+    #[test]
+    fn example_composed_almanac() {
+        let almanac = Almanac::parse(EXAMPLE);
+        let seed_to_location = almanac.mapping("seed", "location").unwrap();
+        let locations = almanac.seeds.iter().map(|seed| seed_to_location.get(*seed)).collect::<Vec<_>>();
+        assert_eq!(locations, vec![82, 43, 86, 35]);
+
+        let location_to_seed = seed_to_location.invert().unwrap();
+        assert_eq!(location_to_seed.get(82), 79);
+        assert_eq!(location_to_seed.get(46), 82);
+
+        let seed_ranges = [Interval::new(79, 93), Interval::new(55, 68)].into_iter().collect();
+        assert_eq!(lowest_location_seed(&seed_ranges, &seed_to_location, &location_to_seed), (82, 46));
+    }
+
+    #[test]
+    fn example_intermediate_categories() {
+        let almanac = Almanac::parse(EXAMPLE);
+        // Seed 79, soil 81, fertilizer 81, water 81, light 74, temperature 78, humidity 78, location 82
+        assert_eq!(almanac.mapping("soil", "light").unwrap().get(81), 74);
+        assert_eq!(almanac.mapping("humidity", "water").unwrap().get(78), 81);
+        assert!(almanac.mapping("seed", "nowhere").is_none());
+    }
+
+    #[test]
+    fn unordered_branching_categories() {
+        let input = "seeds: 1 5
+
+soil-to-water map:
+100 0 10
+
+seed-to-soil map:
+0 1 1
+1 0 1
+
+seed-to-colour map:
+50 0 10
+
+colour-to-shade map:
+20 50 5";
+        let almanac = Almanac::parse(input);
+        assert_eq!(almanac.mapping("seed", "water").unwrap().get(1), 100);
+        assert_eq!(almanac.mapping("seed", "shade").unwrap().get(1), 21);
+        assert_eq!(almanac.mapping("seed", "shade").unwrap().get(7), 57);
+        assert!(almanac.mapping("water", "shade").is_none());
+    }
+
+    #[test]
+    fn example_part2_provenance() {
+        let almanac = Almanac::parse(EXAMPLE);
+        let provenance = nearest_location_for_seed_ranges(&almanac);
+        assert_eq!(provenance.locations.start, 46);
+        assert_eq!(provenance.seeds.start, 82);
+        assert_eq!(provenance.seed_range, Interval::new(79, 93));
+
+        let chain = provenance.map_ranges.iter()
+            .map(|mr| mr.map(|mr| (mr.dest_start, mr.source_start, mr.length)))
+            .collect::<Vec<_>>();
+        assert_eq!(chain, vec![
+            Some((52, 50, 48)),
+            None,
+            None,
+            Some((18, 25, 70)),
+            Some((45, 77, 23)),
+            Some((1, 0, 69)),
+            None,
+        ]);
     }
 }
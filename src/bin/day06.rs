@@ -1,3 +1,9 @@
+use std::fmt;
+use std::ops::{Add, Mul, Sub};
+use std::str::FromStr;
+
+use aoc23::bignum::BigUint;
+
 fn parse_multi(input: &str) -> Vec<(usize, usize)> {
     let mut lines = input.lines();
 
@@ -24,7 +30,7 @@ fn parse_multi(input: &str) -> Vec<(usize, usize)> {
     time.into_iter().zip(distance).collect()
 }
 
-fn parse_single(input: &str) -> (usize, usize) {
+fn parse_single<T: FromStr>(input: &str) -> Result<(T, T), String> where T::Err: fmt::Display {
     let mut lines = input.lines();
 
     let time = lines
@@ -36,7 +42,7 @@ fn parse_single(input: &str) -> (usize, usize) {
         .split_ascii_whitespace()
         .collect::<String>()
         .parse()
-        .unwrap();
+        .map_err(|err| format!("Invalid time: {}", err))?;
 
     let distance = lines
         .next()
@@ -47,35 +53,86 @@ fn parse_single(input: &str) -> (usize, usize) {
         .split_ascii_whitespace()
         .collect::<String>()
         .parse()
-        .unwrap();
+        .map_err(|err| format!("Invalid distance: {}", err))?;
 
-    (time, distance)
+    Ok((time, distance))
 }
 
-fn count_ways_to_beat_record(record: &(usize, usize)) -> usize {
-    let &(time, distance) = record;
-    let mut count = 0;
-    for charge_time in 1..=time {
-        let remaining_time = time - charge_time;
-        let distance_in_remaining_time = charge_time * remaining_time;
-        if distance_in_remaining_time > distance {
-            count += 1;
-        }
+trait RaceNumber: Clone + Ord + From<u8> + Add<Output=Self> + Sub<Output=Self> + Mul<Output=Self> {
+    fn isqrt(&self) -> Self;
+    fn is_odd(&self) -> bool;
+    fn checked_mul(&self, other: &Self) -> Option<Self>;
+}
+
+macro_rules! impl_race_number {
+    ($($t:ty),*) => {
+        $(impl RaceNumber for $t {
+            fn isqrt(&self) -> Self {
+                <$t>::isqrt(*self)
+            }
+
+            fn is_odd(&self) -> bool {
+                self % 2 == 1
+            }
+
+            fn checked_mul(&self, other: &Self) -> Option<Self> {
+                <$t>::checked_mul(*self, *other)
+            }
+        })*
+    };
+}
+
+impl_race_number!(u64, u128, usize);
+
+impl RaceNumber for BigUint {
+    fn isqrt(&self) -> Self {
+        BigUint::isqrt(self)
+    }
+
+    fn is_odd(&self) -> bool {
+        BigUint::is_odd(self)
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        Some(self.clone() * other.clone())
+    }
+}
+
+// None if time^2 or 4 * distance is too big for T
+fn count_ways_to_beat_record<T: RaceNumber>(record: &(T, T)) -> Option<T> {
+    // Charging for c leaves time - c to travel, so we need c * (time - c) > distance. Substituting
+    // k = 2c - time, that's k^2 < time^2 - 4 * distance, and k has the same parity as time.
+    let (time, distance) = record.clone();
+    let zero = T::from(0);
+    let one = T::from(1);
+    let four_distance = T::from(4).checked_mul(&distance)?;
+    let time_squared = time.checked_mul(&time)?;
+    if time_squared <= four_distance {
+        return Some(zero);
+    }
+    let discriminant = time_squared - four_distance;
+
+    // The largest k whose square is strictly less than the discriminant
+    let max_k = (discriminant - one.clone()).isqrt();
+    if max_k.is_odd() == time.is_odd() {
+        Some(max_k + one)
+    } else {
+        Some(max_k)
     }
-    count
 }
 
 fn part1(input: &str) -> usize {
     let race_records = parse_multi(input);
     race_records
         .iter()
-        .map(count_ways_to_beat_record)
+        .map(|record| count_ways_to_beat_record(record).expect("Race too long"))
         .product::<usize>()
 }
 
-fn part2(input: &str) -> usize {
-    let single_race_record = parse_single(input);
-    count_ways_to_beat_record(&single_race_record)
+// The kerned race can be far too long for any fixed width number
+fn part2(input: &str) -> BigUint {
+    let single_race_record = parse_single::<BigUint>(input).unwrap_or_else(|err| panic!("{}", err));
+    count_ways_to_beat_record(&single_race_record).unwrap()
 }
 
 fn main() {
@@ -98,6 +155,38 @@ Distance:  9  40  200";
 
     #[test]
     fn test_part2() {
-        assert_eq!(part2(EXAMPLE), 71503);
+        assert_eq!(part2(EXAMPLE), BigUint::from(71503_u64));
+        assert_eq!(part2("Time: 7000000000000000000000000 0\nDistance: 9").to_string(), "69999999999999999999999999");
+    }
+
+    #[test]
+    fn closed_form_matches_brute_force() {
+        for time in 0..60_u64 {
+            for distance in 0..(time * time / 4 + 2) {
+                let brute_force = (0..=time).filter(|charge| charge * (time - charge) > distance).count() as u64;
+                assert_eq!(count_ways_to_beat_record(&(time, distance)), Some(brute_force), "time {} distance {}", time, distance);
+            }
+        }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn huge_races() {
+        let time = 10_u128.pow(18);
+        assert_eq!(count_ways_to_beat_record(&(time, 0)), Some(time - 1));
+        // Squaring a time past 2^64 doesn't fit in a u128
+        assert_eq!(count_ways_to_beat_record(&(1_u128 << 64, 0)), None);
+        assert_eq!(count_ways_to_beat_record(&(10_u128, u128::MAX / 2)), None);
+
+        let record: (BigUint, BigUint) = parse_single("Time:      7000000000000000000000000  15000000000000000000000000000000000000000000 30
+Distance:  9").unwrap();
+        let ways = count_ways_to_beat_record(&record).unwrap();
+        assert_eq!(ways.to_string(), "70000000000000000000000001500000000000000000000000000000000000000000029");
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(parse_single::<usize>("Time: 7 1 5\nDistance: x"), Err("Invalid distance: invalid digit found in string".to_string()));
+        assert_eq!(parse_single::<usize>("Time: 99999999999999999999999\nDistance: 9"),
+            Err("Invalid time: number too large to fit in target type".to_string()));
+    }
+}
@@ -1,113 +1,301 @@
-#[derive(Debug, PartialEq, Eq)]
-enum HandType {
-    HighCard,
-    OnePair,
-    TwoPair,
-    ThreeOfAKind,
-    FullHouse,
-    FourOfAKind,
-    FiveOfAKind,
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum HandPattern {
+    // Sizes of groups of matching cards, largest first
+    Groups(Vec<usize>),
+    // Cards of consecutive rank, regardless of suit
+    Straight,
 }
-struct Hand {
-    cards: [u8; 5],
-    hand_type: HandType,
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct HandType {
+    name: String,
+    strength: usize,
 }
 
-impl HandType {
-    fn calculate(hand: &[u8; 5]) -> HandType {
-        let mut counts = [0; 15];
-        for card in hand {
-            counts[*card as usize] += 1;
-        }
-        let mut counts = counts.into_iter().collect::<Vec<_>>();
-        let js = counts.remove(1); // Remove the jokers
-        counts.sort_unstable();
-        counts.reverse();
-        counts[0] += js; // Add the jokers to the biggest group
-        match counts[0..5] {
-            [1, 1, 1, 1, 1] => HandType::HighCard,
-            [2, 1, 1, 1, 0] => HandType::OnePair,
-            [2, 2, 1, 0, 0] => HandType::TwoPair,
-            [3, 1, 1, 0, 0] => HandType::ThreeOfAKind,
-            [3, 2, 0, 0, 0] => HandType::FullHouse,
-            [4, 1, 0, 0, 0] => HandType::FourOfAKind,
-            [5, 0, 0, 0, 0] => HandType::FiveOfAKind,
-            _ => unreachable!(),
+struct Rules {
+    // Weakest first
+    ranks: Vec<char>,
+    wildcards: Vec<char>,
+    // The order cards run in for straights, which can differ from ranks when a wildcard is ranked
+    // below its face value
+    run_order: Vec<char>,
+    hand_size: usize,
+    // Weakest first
+    hand_types: Vec<(String, HandPattern)>,
+}
+
+impl Rules {
+    fn new(ranks: &str, wildcards: &str, hand_size: usize, hand_types: &[(&str, HandPattern)]) -> Rules {
+        Rules {
+            ranks: ranks.chars().collect(),
+            wildcards: wildcards.chars().collect(),
+            run_order: ranks.chars().filter(|c| !wildcards.contains(*c)).collect(),
+            hand_size,
+            hand_types: hand_types.iter().map(|(name, pattern)| (name.to_string(), pattern.clone())).collect(),
         }
     }
 
-    fn strength(&self) -> u8 {
-        match self {
-            HandType::HighCard => 1,
-            HandType::OnePair => 2,
-            HandType::TwoPair => 3,
-            HandType::ThreeOfAKind => 4,
-            HandType::FullHouse => 5,
-            HandType::FourOfAKind => 6,
-            HandType::FiveOfAKind => 7,
+    fn with_run_order(mut self, run_order: &str) -> Rules {
+        self.run_order = run_order.chars().collect();
+        self
+    }
+
+    // Optionally with straights ranked between three of a kind and a full house, as in poker
+    fn standard_hand_types(straights: bool) -> Vec<(&'static str, HandPattern)> {
+        let mut hand_types = vec![
+            ("High card", HandPattern::Groups(vec![1, 1, 1, 1, 1])),
+            ("One pair", HandPattern::Groups(vec![2, 1, 1, 1])),
+            ("Two pair", HandPattern::Groups(vec![2, 2, 1])),
+            ("Three of a kind", HandPattern::Groups(vec![3, 1, 1])),
+            ("Full house", HandPattern::Groups(vec![3, 2])),
+            ("Four of a kind", HandPattern::Groups(vec![4, 1])),
+            ("Five of a kind", HandPattern::Groups(vec![5])),
+        ];
+        if straights {
+            hand_types.insert(4, ("Straight", HandPattern::Straight));
         }
+        hand_types
+    }
+
+    fn part1(straights: bool) -> Rules {
+        Rules::new("23456789TJQKA", "", 5, &Rules::standard_hand_types(straights))
+    }
+
+    fn part2(straights: bool) -> Rules {
+        // Jokers rank lowest, but a joker standing in for itself still sits between T and Q in a straight
+        Rules::new("J23456789TQKA", "J", 5, &Rules::standard_hand_types(straights))
+            .with_run_order("23456789TJQKA")
+    }
+
+    fn rank(&self, card: char) -> u8 {
+        self.ranks.iter().position(|c| *c == card).unwrap_or_else(|| panic!("Invalid card {}", card)) as u8
+    }
+
+    fn is_wild(&self, card: char) -> bool {
+        self.wildcards.contains(&card)
+    }
+
+    // What the cards look like with each wildcard replaced by the card it stands in for, if they can make
+    // the given pattern
+    fn resolve(&self, cards: &[char], pattern: &HandPattern) -> Option<Vec<char>> {
+        let natural_cards = cards.iter().copied().filter(|c| !self.is_wild(*c)).collect::<Vec<_>>();
+        let stand_ins = match pattern {
+            HandPattern::Groups(sizes) => {
+                let mut counts = HashMap::new();
+                for card in &natural_cards {
+                    *counts.entry(*card).or_insert(0) += 1;
+                }
+                let mut groups = counts.iter().map(|(card, count)| (*card, *count)).collect::<Vec<_>>();
+                groups.sort_unstable_by(|(a_card, a_count), (b_card, b_count)| {
+                    b_count.cmp(a_count).then_with(|| self.rank(*b_card).cmp(&self.rank(*a_card)))
+                });
+                // Wildcards can top up any group, so each natural group just has to fit in a group of the pattern
+                let fits = sizes.iter().sum::<usize>() == cards.len()
+                    && groups.len() <= sizes.len()
+                    && groups.iter().zip(sizes).all(|((_, count), size)| count <= size);
+                if !fits {
+                    return None;
+                }
+
+                // Groups made up entirely of wildcards take the best ranks not otherwise in the hand
+                let mut unused_ranks = self.ranks.iter().rev().filter(|c| !self.is_wild(**c) && !counts.contains_key(c));
+                let mut stand_ins = Vec::new();
+                for (i, size) in sizes.iter().enumerate() {
+                    let (card, count) = match groups.get(i) {
+                        Some(group) => *group,
+                        None => (*unused_ranks.next()?, 0),
+                    };
+                    stand_ins.extend(std::iter::repeat_n(card, size - count));
+                }
+                stand_ins
+            },
+            HandPattern::Straight => {
+                // Wildcards can fill any gaps in the run, but every natural card keeps its own place in it
+                let run_order = &self.run_order;
+                let mut positions = natural_cards.iter()
+                    .map(|card| run_order.iter().position(|c| c == card).unwrap())
+                    .collect::<Vec<_>>();
+                positions.sort_unstable();
+                positions.dedup();
+                let fits = positions.len() == natural_cards.len()
+                    && run_order.len() >= cards.len()
+                    && positions.first().zip(positions.last()).is_none_or(|(min, max)| max - min < cards.len());
+                if !fits {
+                    return None;
+                }
+
+                // Wildcards fill the highest run that includes all the natural cards
+                let lowest = positions.first().copied().unwrap_or(usize::MAX).min(run_order.len() - cards.len());
+                (lowest..lowest + cards.len())
+                    .filter(|position| !positions.contains(position))
+                    .map(|position| run_order[position])
+                    .collect()
+            },
+        };
+
+        let mut stand_ins = stand_ins.into_iter();
+        Some(cards.iter().map(|c| if self.is_wild(*c) { stand_ins.next().unwrap() } else { *c }).collect())
+    }
+
+    fn classify(&self, cards: &[char]) -> (HandType, Vec<char>) {
+        self.hand_types.iter()
+            .enumerate()
+            .rev()
+            .find_map(|(strength, (name, pattern))| {
+                self.resolve(cards, pattern).map(|resolved| (HandType { name: name.clone(), strength }, resolved))
+            })
+            .unwrap_or_else(|| panic!("No hand type matches {}", cards.iter().collect::<String>()))
     }
 }
 
+// Hands are only comparable with other hands parsed under the same rules
+#[derive(Debug)]
+struct Hand {
+    cards: String,
+    // The cards with wildcards replaced by what they were treated as
+    resolved: String,
+    ranks: Vec<u8>,
+    hand_type: HandType,
+}
+
 impl Hand {
-    fn parse(input: &str, j_value: u8) -> Hand {
-        let mut cards = [0; 5];
-        for (i, card) in input.chars().enumerate() {
-            let card = match card {
-                'T' => 10,
-                'J' => j_value,
-                'Q' => 12,
-                'K' => 13,
-                'A' => 14,
-                c => c.to_digit(10).unwrap() as u8,
-            };
-            cards[i] = card;
-        }
-        let hand_type = HandType::calculate(&cards);
-        Hand { cards, hand_type }
+    fn parse(input: &str, rules: &Rules) -> Hand {
+        let cards = input.chars().collect::<Vec<_>>();
+        assert_eq!(cards.len(), rules.hand_size, "Hand {} is the wrong size", input);
+        let ranks = cards.iter().map(|card| rules.rank(*card)).collect();
+        let (hand_type, resolved) = rules.classify(&cards);
+        Hand { cards: input.to_string(), resolved: resolved.into_iter().collect(), ranks, hand_type }
     }
 }
 
-fn parse(input: &str, j_value: u8) -> Vec<(Hand, usize)> {
+impl Ord for Hand {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.hand_type.strength.cmp(&other.hand_type.strength)
+            .then_with(|| self.ranks.cmp(&other.ranks))
+    }
+}
+
+impl PartialOrd for Hand {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Hand {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Hand {}
+
+fn parse(input: &str, rules: &Rules) -> Vec<(Hand, usize)> {
     input.lines()
         .map(|line| {
             line.split_once(' ')
-                .map(|(hand, bid)| (Hand::parse(hand, j_value), bid.parse().unwrap()))
+                .map(|(hand, bid)| (Hand::parse(hand, rules), bid.parse().unwrap()))
                 .unwrap()
         })
         .collect()
 }
 
-fn compare_hands(a: &Hand, b: &Hand) -> std::cmp::Ordering {
-    let a_strength = a.hand_type.strength();
-    let b_strength = b.hand_type.strength();
-    if a_strength == b_strength {
-        a.cards.cmp(&b.cards)
-    } else {
-        a_strength.cmp(&b_strength)
+#[derive(Debug, PartialEq, Eq)]
+enum TieBreak {
+    // The hands are of different types
+    HandType,
+    // The hands are the same type, and the first difference is the card at this index
+    Card(usize),
+    Tied,
+}
+
+impl TieBreak {
+    fn between(lower: &Hand, higher: &Hand) -> TieBreak {
+        if lower.hand_type.strength != higher.hand_type.strength {
+            return TieBreak::HandType;
+        }
+        match lower.ranks.iter().zip(&higher.ranks).position(|(l, h)| l != h) {
+            Some(index) => TieBreak::Card(index),
+            None => TieBreak::Tied,
+        }
+    }
+}
+
+struct RankedHand {
+    hand: Hand,
+    bid: usize,
+    rank: usize,
+    // How this hand was separated from the one ranked immediately below it
+    tie_break: Option<TieBreak>,
+}
+
+impl RankedHand {
+    fn winnings(&self) -> usize {
+        self.rank * self.bid
     }
+
+    fn explain(&self) -> String {
+        let mut explanation = format!("{:>5}. {}", self.rank, self.hand.cards);
+        if self.hand.resolved != self.hand.cards {
+            explanation += &format!(" (as {})", self.hand.resolved);
+        }
+        explanation += &format!(": {}, bid {} wins {}", self.hand.hand_type.name, self.bid, self.winnings());
+        match self.tie_break {
+            Some(TieBreak::HandType) => explanation += "; beats the hand below on type",
+            Some(TieBreak::Card(index)) => explanation += &format!("; beats the hand below on card {}", index + 1),
+            Some(TieBreak::Tied) => explanation += "; ties with the hand below",
+            None => {},
+        }
+        explanation
+    }
+}
+
+fn rank_hands(input: &str, rules: &Rules) -> Vec<RankedHand> {
+    let mut hands = parse(input, rules);
+    hands.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+
+    let tie_breaks = std::iter::once(None)
+        .chain(hands.windows(2).map(|pair| Some(TieBreak::between(&pair[0].0, &pair[1].0))))
+        .collect::<Vec<_>>();
+    hands.into_iter().zip(tie_breaks).enumerate()
+        .map(|(i, ((hand, bid), tie_break))| RankedHand { hand, bid, rank: i + 1, tie_break })
+        .collect()
 }
 
-fn total_winnings(input: &str, j_value: u8) -> usize {
-    let mut hands = parse(input, j_value);
-    hands.sort_unstable_by(|(a, _), (b, _)| compare_hands(a, b));
-    hands.iter().enumerate()
-        .map(|(i, (_, bid))| (i + 1) * bid)
+fn total_winnings(input: &str, rules: &Rules) -> usize {
+    rank_hands(input, rules).iter()
+        .map(|ranked| ranked.winnings())
         .sum()
 }
 
 fn part1(input: &str) -> usize {
-    total_winnings(input, 11)
+    total_winnings(input, &Rules::part1(false))
 }
 
 fn part2(input: &str) -> usize {
-    total_winnings(input, 1)
+    total_winnings(input, &Rules::part2(false))
 }
 
 fn main() {
     let input = include_str!("../../input/day07");
     println!("Part 1: {}", part1(input));
     println!("Part 2: {}", part2(input));
+
+    if std::env::args().any(|arg| arg == "--explain") {
+        for (part, rules) in [(1, Rules::part1(false)), (2, Rules::part2(false))] {
+            println!("Part {} rankings:", part);
+            for ranked in rank_hands(input, &rules) {
+                println!("{}", ranked.explain());
+            }
+        }
+    }
+
+    if std::env::args().any(|arg| arg == "--straights") {
+        println!("Part 1 with straights: {}", total_winnings(input, &Rules::part1(true)));
+        println!("Part 2 with straights: {}", total_winnings(input, &Rules::part2(true)));
+    }
 }
 
 #[cfg(test)]
@@ -120,15 +308,20 @@ KK677 28
 KTJJT 220
 QQQJA 483";
 
+    fn hand_type_name(rules: &Rules, hand: &str) -> String {
+        Hand::parse(hand, rules).hand_type.name
+    }
+
     #[test]
     fn test_calculate_hand_type() {
-        assert_eq!(HandType::calculate(&[2, 2, 2, 2, 2]), HandType::FiveOfAKind);
-        assert_eq!(HandType::calculate(&[2, 2, 2, 2, 3]), HandType::FourOfAKind);
-        assert_eq!(HandType::calculate(&[2, 2, 2, 3, 3]), HandType::FullHouse);
-        assert_eq!(HandType::calculate(&[6, 2, 3, 3, 3]), HandType::ThreeOfAKind);
-        assert_eq!(HandType::calculate(&[2, 3, 3, 4, 4]), HandType::TwoPair);
-        assert_eq!(HandType::calculate(&[6, 2, 3, 4, 4]), HandType::OnePair);
-        assert_eq!(HandType::calculate(&[6, 2, 3, 4, 5]), HandType::HighCard);
+        let rules = Rules::part1(false);
+        assert_eq!(hand_type_name(&rules, "22222"), "Five of a kind");
+        assert_eq!(hand_type_name(&rules, "22223"), "Four of a kind");
+        assert_eq!(hand_type_name(&rules, "22233"), "Full house");
+        assert_eq!(hand_type_name(&rules, "62333"), "Three of a kind");
+        assert_eq!(hand_type_name(&rules, "23344"), "Two pair");
+        assert_eq!(hand_type_name(&rules, "62344"), "One pair");
+        assert_eq!(hand_type_name(&rules, "62345"), "High card");
     }
 
     #[test]
@@ -140,4 +333,71 @@ QQQJA 483";
     fn test_part2() {
         assert_eq!(part2(EXAMPLE), 5905);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_custom_rules() {
+        // Four card hands with two kinds of wildcard, and straights that beat three of a kind
+        let rules = Rules::new("W23456789TJQKAX", "WX", 4, &[
+            ("High card", HandPattern::Groups(vec![1, 1, 1, 1])),
+            ("Pair", HandPattern::Groups(vec![2, 1, 1])),
+            ("Two pair", HandPattern::Groups(vec![2, 2])),
+            ("Three of a kind", HandPattern::Groups(vec![3, 1])),
+            ("Straight", HandPattern::Straight),
+            ("Four of a kind", HandPattern::Groups(vec![4])),
+        ]);
+        assert_eq!(hand_type_name(&rules, "29KA"), "High card");
+        assert_eq!(hand_type_name(&rules, "2345"), "Straight");
+        assert_eq!(hand_type_name(&rules, "2W45"), "Straight");
+        assert_eq!(hand_type_name(&rules, "X34K"), "Pair");
+        assert_eq!(hand_type_name(&rules, "TXWK"), "Straight");
+        assert_eq!(hand_type_name(&rules, "TXWA"), "Three of a kind");
+        assert_eq!(hand_type_name(&rules, "XX22"), "Four of a kind");
+        assert_eq!(hand_type_name(&rules, "XWKK"), "Four of a kind");
+
+        // Same type, so the wildcard's low rank decides it
+        assert!(Hand::parse("W345", &rules) < Hand::parse("2345", &rules));
+        assert!(Hand::parse("X345", &rules) > Hand::parse("2345", &rules));
+        assert!(Hand::parse("2W45", &rules) > Hand::parse("29KA", &rules));
+    }
+
+    #[test]
+    fn test_standard_straights() {
+        let rules = Rules::part2(true);
+        assert_eq!(hand_type_name(&rules, "9TJQK"), "Straight");
+        assert_eq!(hand_type_name(&rules, "23456"), "Straight");
+        assert_eq!(hand_type_name(&rules, "TQKA2"), "High card");
+        assert_eq!(hand_type_name(&Rules::part2(true), "9TQKA"), "High card");
+        assert_eq!(hand_type_name(&Rules::part2(true), "9TQKJ"), "Straight");
+        assert!(Hand::parse("23456", &rules) > Hand::parse("QQQ2A", &rules));
+        assert!(Hand::parse("23456", &rules) < Hand::parse("22333", &rules));
+    }
+
+    #[test]
+    fn test_wildcard_resolution() {
+        let rules = Rules::part2(false);
+        assert_eq!(Hand::parse("KTJJT", &rules).resolved, "KTTTT");
+        assert_eq!(Hand::parse("QQQJA", &rules).resolved, "QQQQA");
+        assert_eq!(Hand::parse("JJJJJ", &rules).resolved, "AAAAA");
+        assert_eq!(Hand::parse("2J4J6", &rules).resolved, "26466");
+        assert_eq!(Hand::parse("9TJQK", &Rules::part2(true)).resolved, "9TJQK");
+        assert_eq!(Hand::parse("9TJQ8", &Rules::part2(true)).resolved, "9TJQ8");
+        assert_eq!(Hand::parse("9TJQ8", &Rules::part2(true)).hand_type.name, "Straight");
+        assert_eq!(Hand::parse("J2345", &Rules::part2(true)).resolved, "62345");
+    }
+
+    #[test]
+    fn test_rank_explanations() {
+        let ranked = rank_hands(EXAMPLE, &Rules::part2(false));
+        let summary = ranked.iter()
+            .map(|r| (r.hand.cards.as_str(), r.hand.resolved.as_str(), r.rank, r.winnings(), &r.tie_break))
+            .collect::<Vec<_>>();
+        assert_eq!(summary, vec![
+            ("32T3K", "32T3K", 1, 765, &None),
+            ("KK677", "KK677", 2, 56, &Some(TieBreak::HandType)),
+            ("T55J5", "T5555", 3, 2052, &Some(TieBreak::HandType)),
+            ("QQQJA", "QQQQA", 4, 1932, &Some(TieBreak::Card(0))),
+            ("KTJJT", "KTTTT", 5, 1100, &Some(TieBreak::Card(0))),
+        ]);
+        assert_eq!(ranked[4].explain(), "    5. KTJJT (as KTTTT): Four of a kind, bid 220 wins 1100; beats the hand below on card 1");
+    }
+}
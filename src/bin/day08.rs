@@ -1,39 +1,86 @@
 use std::collections::HashMap;
 
-fn parse(input: &str) -> (Vec<u8>, HashMap<String, [String; 2]>) {
-    let mut lines = input.lines();
-
-    let turns = lines.next()
-        .unwrap()
-        .bytes()
-        .map(|b| match b {
-            b'L' => 0_u8,
-            b'R' => 1,
-            _ => unreachable!("Invalid input"),
-        })
-        .collect::<Vec<_>>();
-    lines.next();
+// The network with each node name interned as a dense id, so walking it is just indexing into arrays
+struct Network {
+    turns: Vec<u8>,
+    names: Vec<String>,
+    ids: HashMap<String, usize>,
+    // Left and right neighbour of each node
+    next: Vec<[usize; 2]>,
+    is_z_node: Vec<bool>,
+}
+
+impl Network {
+    fn parse(input: &str) -> Network {
+        let mut lines = input.lines();
+
+        let turns = lines.next()
+            .unwrap()
+            .bytes()
+            .map(|b| match b {
+                b'L' => 0_u8,
+                b'R' => 1,
+                _ => unreachable!("Invalid input"),
+            })
+            .collect::<Vec<_>>();
+        lines.next();
+
+        let mut network = Network { turns, names: Vec::new(), ids: HashMap::new(), next: Vec::new(), is_z_node: Vec::new() };
+        for line in lines {
+            let from = network.intern(&line[0..3]);
+            let left = network.intern(&line[7..10]);
+            let right = network.intern(&line[12..15]);
+            network.next[from] = [left, right];
+        }
+
+        network
+    }
+
+    fn intern(&mut self, name: &str) -> usize {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+        let id = self.names.len();
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), id);
+        self.next.push([id, id]);
+        self.is_z_node.push(name.ends_with('Z'));
+        id
+    }
+
+    fn id(&self, name: &str) -> usize {
+        self.ids[name]
+    }
+
+    fn name(&self, id: usize) -> &str {
+        &self.names[id]
+    }
+
+    fn node_count(&self) -> usize {
+        self.names.len()
+    }
+
+    fn start_nodes(&self) -> Vec<usize> {
+        (0..self.node_count()).filter(|id| self.names[*id].ends_with('A')).collect()
+    }
 
-    let mut map = HashMap::new();
-    for line in lines {
-        let from = line[0..3].to_string();
-        let left = line[7..10].to_string();
-        let right = line[12..15].to_string();
-        map.insert(from, [left, right]);
+    fn step(&self, node: usize, count: usize) -> usize {
+        self.next[node][self.turns[count % self.turns.len()] as usize]
     }
 
-    (turns, map)
+    fn walk(&self, start: usize, steps: usize) -> usize {
+        (0..steps).fold(start, |node, count| self.step(node, count))
+    }
 }
 
 fn part1(input: &str) -> usize {
-    let (turns, map) = parse(input);
+    let network = Network::parse(input);
 
-    let mut current = "AAA".to_string();
+    let end = network.id("ZZZ");
+    let mut current = network.id("AAA");
     let mut count = 0;
-    while current != "ZZZ" {
-        let turn = turns[count % turns.len()];
-        let next = &map[&current][turn as usize];
-        current = next.to_string();
+    while current != end {
+        current = network.step(current, count);
         count += 1;
     }
     count
@@ -42,20 +89,16 @@ fn part1(input: &str) -> usize {
 // Let's just guess / hope that the answer is the lowest common multiple of the number of steps
 // it takes to reach a Z node from each starting A node.
 // This is true for the example input, and feels like the kind of optimisation AoC would include,
-// but I can't see that it's necessarily true!
+// but I can't see that it's necessarily true! (part2_general below doesn't rely on it.)
 fn part2(input: &str) -> usize {
-    let (turns, map) = parse(input);
-
-    let start_locations = map.keys().filter(|k| k.ends_with('A')).collect::<Vec<_>>();
+    let network = Network::parse(input);
 
-    let num_steps = start_locations.into_iter()
+    let num_steps = network.start_nodes().into_iter()
         .map(|start_location| {
             let mut current = start_location;
             let mut count = 0;
-            while !current.ends_with('Z') {
-                let turn = turns[count % turns.len()];
-                let next = &map[current][turn as usize];
-                current = next;
+            while !network.is_z_node[current] {
+                current = network.step(current, count);
                 count += 1;
             }
             count
@@ -81,76 +124,183 @@ fn gcd(a: usize, b: usize) -> usize {
     }
 }
 
+// Where a ghost's walk settles into a loop, and the steps at which it is on a node ending in Z. Every step
+// from loop_start onwards repeats loop_length steps later, so z_steps only covers the first pass of the loop.
+#[derive(Debug, PartialEq, Eq)]
+struct GhostLoop {
+    loop_start: usize,
+    loop_length: usize,
+    z_steps: Vec<usize>,
+}
+
+impl GhostLoop {
+    fn is_z_step(&self, step: usize) -> bool {
+        if step < self.loop_start {
+            self.z_steps.contains(&step)
+        } else {
+            self.z_steps.iter()
+                .filter(|z| **z >= self.loop_start)
+                .any(|z| step.abs_diff(*z).is_multiple_of(self.loop_length))
+        }
+    }
+}
+
 // Given a starting point, find how long it takes until the sequence loops back to the same place, and
 // at which points it passes through a node ending in Z.
-fn find_loop(turns: &[u8], map: &HashMap<String, [String; 2]>, start: &String) -> (usize, Vec<usize>) {
-    let mut history = HashMap::new();
-    let mut z_node_counts = Vec::new();
+fn find_loop(network: &Network, start: usize) -> GhostLoop {
+    // The step at which we were first at each (turn index, node), indexed by turn index * node count + node
+    let mut history = vec![usize::MAX; network.turns.len() * network.node_count()];
+    let state = |count: usize, node: usize| (count % network.turns.len()) * network.node_count() + node;
+    let mut z_steps = Vec::new();
     let mut current = start;
     let mut count = 0;
 
-    while !history.contains_key(&(count % turns.len(), current)) {
+    while history[state(count, current)] == usize::MAX {
         // Record that we've been here
-        history.insert((count % turns.len(), current), count);
+        history[state(count, current)] = count;
 
         // Record if this is a possible end node
-        if current.ends_with('Z') {
-            z_node_counts.push(count);
+        if network.is_z_node[current] {
+            z_steps.push(count);
         }
 
         // Advance to the next location
-        let turn = turns[count % turns.len()];
-        let next = &map[current][turn as usize];
-        current = next;
+        current = network.step(current, count);
         count += 1;
     }
-    let start_of_loop = history[&(count % turns.len(), current)];
+    let loop_start = history[state(count, current)];
+
+    GhostLoop { loop_start, loop_length: count - loop_start, z_steps }
+}
 
-    let loop_length = count - start_of_loop;
-    
-    (loop_length, z_node_counts)
+// Merges x = a1 (mod m1) and x = a2 (mod m2) into a single congruence modulo lcm(m1, m2), if they agree.
+// The moduli needn't be coprime.
+fn merge_congruences((a1, m1): (i128, i128), (a2, m2): (i128, i128)) -> Option<(i128, i128)> {
+    let (g, p, _) = extended_gcd(m1, m2);
+    if (a2 - a1) % g != 0 {
+        return None;
+    }
+    let lcm = m1 / g * m2;
+    // m1 * p = g (mod m2), so stepping a1 by multiples of m1 reaches a2 after ((a2 - a1) / g) * p of them
+    let k = ((a2 - a1) / g * p).rem_euclid(m2 / g);
+    Some(((a1 + m1 * k).rem_euclid(lcm), lcm))
 }
 
-// Check whether a given step would place a given loop on a Z node
-fn is_z_node((loop_length, z_node_counts): &(usize, Vec<usize>), step: usize) -> bool {
-    z_node_counts.iter().any(|z| (step - z) % loop_length == 0)
+// Returns (g, x, y) such that a * x + b * y = g = gcd(a, b)
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (g, x, y) = extended_gcd(b, a % b);
+        (g, y, x - (a / b) * y)
+    }
 }
 
-fn find_simultaneous_z_node_count(turns: &[u8], map: &HashMap<String, [String; 2]>, starts: &[&String]) -> usize {
-    // Find the loop length and z node counts for each starting point
-    let mut loop_lengths_and_z_node_counts = starts.iter()
-        .map(|start| find_loop(turns, map, start))
-        .collect::<Vec<_>>();
-    loop_lengths_and_z_node_counts.sort_unstable_by_key(|(_, z_node_counts)| z_node_counts.len());
-    loop_lengths_and_z_node_counts.reverse();
-    
-    let (loop_length, z_node_counts) = loop_lengths_and_z_node_counts.pop().unwrap();
-
-    let mut iteration = 1;
-    loop {
-        for z in &z_node_counts {
-            let step = iteration * loop_length + z;
-            if loop_lengths_and_z_node_counts.iter().all(|loop_info| is_z_node(loop_info, step)) {
-                return step;
-            }
-        }
+// The first step at which every ghost is on a Z node, if there is one
+fn find_simultaneous_z_step(loops: &[GhostLoop]) -> Option<usize> {
+    // Before every ghost is in its loop, just check each step directly
+    let all_looping_from = loops.iter().map(|l| l.loop_start).max().unwrap_or(0);
+    if let Some(step) = (0..all_looping_from).find(|step| loops.iter().all(|l| l.is_z_step(*step))) {
+        return Some(step);
+    }
 
-        iteration += 1;
+    // After that, each ghost is on a Z node at any step congruent to one of its in-loop Z steps. Combine
+    // every choice of Z step per ghost with the Chinese Remainder Theorem.
+    let mut congruences = vec![(0_i128, 1_i128)];
+    for ghost_loop in loops {
+        let loop_length = ghost_loop.loop_length as i128;
+        congruences = congruences.iter()
+            .flat_map(|&congruence| {
+                ghost_loop.z_steps.iter()
+                    .filter(|z| **z >= ghost_loop.loop_start)
+                    .filter_map(move |&z| merge_congruences(congruence, (z as i128 % loop_length, loop_length)))
+            })
+            .collect();
+        congruences.sort_unstable();
+        congruences.dedup();
     }
+
+    let all_looping_from = all_looping_from as i128;
+    congruences.into_iter()
+        .map(|(a, m)| {
+            if a >= all_looping_from {
+                a
+            } else {
+                a + (all_looping_from - a + m - 1) / m * m
+            }
+        })
+        .min()
+        .map(|step| step as usize)
 }
 
-fn part2_general(input: &str) -> usize{
-    let (turns, map) = parse(input);
+fn part2_general(input: &str) -> Option<usize> {
+    let network = Network::parse(input);
 
-    let start_locations = map.keys()
-        .filter(|k| k.ends_with('A'))
+    let loops = network.start_nodes().into_iter()
+        .map(|start| find_loop(&network, start))
         .collect::<Vec<_>>();
 
-    find_simultaneous_z_node_count(&turns, &map, &start_locations)
+    find_simultaneous_z_step(&loops)
+}
+
+// Renders the network as Graphviz DOT, labelling each ghost's start, where it enters its loop, and the Z nodes
+// it visits. If collapsed, each edge is a whole period of the L/R instructions rather than a single step.
+fn to_dot(network: &Network, collapsed: bool) -> String {
+    let mut lines = vec!["digraph network {".to_string()];
+
+    for node in 0..network.node_count() {
+        let name = network.name(node);
+        if collapsed {
+            let next = network.walk(node, network.turns.len());
+            lines.push(format!("    \"{}\" -> \"{}\";", name, network.name(next)));
+        } else {
+            let [left, right] = network.next[node];
+            if left == right {
+                lines.push(format!("    \"{}\" -> \"{}\" [label=\"L/R\"];", name, network.name(left)));
+            } else {
+                lines.push(format!("    \"{}\" -> \"{}\" [label=\"L\"];", name, network.name(left)));
+                lines.push(format!("    \"{}\" -> \"{}\" [label=\"R\"];", name, network.name(right)));
+            }
+        }
+    }
+
+    let mut annotations: HashMap<usize, Vec<String>> = HashMap::new();
+    for (ghost, start) in network.start_nodes().into_iter().enumerate() {
+        let ghost_loop = find_loop(network, start);
+        annotations.entry(start).or_default().push(format!("ghost {} start", ghost));
+        let loop_entry = network.walk(start, ghost_loop.loop_start);
+        annotations.entry(loop_entry).or_default().push(format!(
+            "ghost {} loop entry at step {} (length {})", ghost, ghost_loop.loop_start, ghost_loop.loop_length
+        ));
+        for z_step in &ghost_loop.z_steps {
+            let z_node = network.walk(start, *z_step);
+            annotations.entry(z_node).or_default().push(format!("ghost {} Z at step {}", ghost, z_step));
+        }
+    }
+    let mut annotated_nodes = annotations.keys().copied().collect::<Vec<_>>();
+    annotated_nodes.sort_unstable();
+    for node in annotated_nodes {
+        let shape = if network.is_z_node[node] { "doublecircle" } else { "box" };
+        lines.push(format!(
+            "    \"{}\" [shape={}, style=filled, fillcolor=lightblue, xlabel=\"{}\"];",
+            network.name(node), shape, annotations[&node].join("\\n")
+        ));
+    }
+
+    lines.push("}".to_string());
+    lines.join("\n")
 }
 
 fn main() {
     let input = include_str!("../../input/day08");
+
+    let args = std::env::args().collect::<Vec<_>>();
+    if args.iter().any(|arg| arg == "--dot" || arg == "--dot-collapsed") {
+        let collapsed = args.iter().any(|arg| arg == "--dot-collapsed");
+        println!("{}", to_dot(&Network::parse(input), collapsed));
+        return;
+    }
+
     let num_turns_p1 = part1(input);
     println!("Part 1: {}", num_turns_p1);
 
@@ -158,8 +308,10 @@ fn main() {
     println!("Part 2: {}", num_turns_p2);
 
     // Alternative, more general solution
-    let num_turns_p2_general = part2_general(input);
-    println!("Part 2: {}", num_turns_p2_general);
+    match part2_general(input) {
+        Some(num_turns_p2_general) => println!("Part 2: {}", num_turns_p2_general),
+        None => println!("Part 2: the ghosts are never all on Z nodes at once"),
+    }
 }
 
 #[cfg(test)]
@@ -192,4 +344,81 @@ XXX = (XXX, XXX)";
     fn test_part2() {
         assert_eq!(part2(EXAMPLE_2), 6);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_part2_general() {
+        assert_eq!(part2_general(EXAMPLE_2), Some(6));
+    }
+
+    #[test]
+    fn test_find_loop() {
+        let network = Network::parse(EXAMPLE_2);
+        assert_eq!(find_loop(&network, network.id("11A")), GhostLoop { loop_start: 1, loop_length: 2, z_steps: vec![2] });
+        assert_eq!(find_loop(&network, network.id("22A")), GhostLoop { loop_start: 1, loop_length: 6, z_steps: vec![3, 6] });
+    }
+
+    #[test]
+    fn test_simultaneous_z_step_with_non_coprime_loops() {
+        let loops = [
+            GhostLoop { loop_start: 2, loop_length: 6, z_steps: vec![5] },
+            GhostLoop { loop_start: 0, loop_length: 4, z_steps: vec![3] },
+        ];
+        // 5, 11, 17, 23... and 3, 7, 11, 15...
+        assert_eq!(find_simultaneous_z_step(&loops), Some(11));
+
+        let loops = [
+            GhostLoop { loop_start: 0, loop_length: 6, z_steps: vec![2] },
+            GhostLoop { loop_start: 0, loop_length: 4, z_steps: vec![1, 3] },
+        ];
+        // Even steps and odd steps never meet
+        assert_eq!(find_simultaneous_z_step(&loops), None);
+    }
+
+    #[test]
+    fn test_simultaneous_z_step_before_loops() {
+        let loops = [
+            GhostLoop { loop_start: 4, loop_length: 3, z_steps: vec![1, 5] },
+            GhostLoop { loop_start: 0, loop_length: 1, z_steps: vec![0] },
+        ];
+        assert_eq!(find_simultaneous_z_step(&loops), Some(1));
+
+        let loops = [
+            GhostLoop { loop_start: 4, loop_length: 3, z_steps: vec![1, 5] },
+            GhostLoop { loop_start: 2, loop_length: 5, z_steps: vec![3, 4] },
+        ];
+        // The first ghost only hits 1 before looping; after that 5, 8, 11, 14... against 3, 4, 8, 9, 13, 14...
+        assert_eq!(find_simultaneous_z_step(&loops), Some(8));
+
+        let loops = [
+            GhostLoop { loop_start: 4, loop_length: 3, z_steps: vec![2, 4] },
+            GhostLoop { loop_start: 0, loop_length: 2, z_steps: vec![1] },
+        ];
+        // Step 3 would be in the first ghost's loop, but it hasn't reached the loop yet
+        assert_eq!(find_simultaneous_z_step(&loops), Some(7));
+    }
+
+    #[test]
+    fn test_interned_network() {
+        let network = Network::parse(EXAMPLE_1);
+        assert_eq!(network.node_count(), 3);
+        let aaa = network.id("AAA");
+        assert_eq!(network.name(network.step(aaa, 0)), "BBB");
+        assert_eq!(network.name(network.walk(aaa, 6)), "ZZZ");
+        assert_eq!(network.start_nodes(), vec![aaa]);
+    }
+
+    #[test]
+    fn test_dot_export() {
+        let network = Network::parse(EXAMPLE_2);
+        let dot = to_dot(&network, false);
+        assert!(dot.starts_with("digraph network {\n"));
+        assert!(dot.contains("    \"11A\" -> \"11B\" [label=\"L\"];\n"));
+        assert!(dot.contains("    \"22B\" -> \"22C\" [label=\"L/R\"];\n"));
+        assert!(dot.contains("    \"11B\" [shape=box, style=filled, fillcolor=lightblue, xlabel=\"ghost 0 loop entry at step 1 (length 2)\"];"));
+        assert!(dot.contains("    \"22Z\" [shape=doublecircle, style=filled, fillcolor=lightblue, xlabel=\"ghost 1 Z at step 3\\nghost 1 Z at step 6\"];"));
+
+        let collapsed = to_dot(&network, true);
+        assert!(collapsed.contains("    \"22A\" -> \"22C\";\n"));
+        assert!(collapsed.contains("    \"11Z\" -> \"11Z\";\n"));
+    }
+}
@@ -1,45 +1,319 @@
-#![feature(iter_map_windows)]
+use std::fmt;
 
-fn parse(input: &str) -> Vec<Vec<isize>> {
+fn parse(input: &str) -> Vec<Vec<i128>> {
     input.lines().map(|line| {
         line.split_whitespace().map(|num| num.parse().unwrap()).collect()
     }).collect()
 }
 
-fn derivatives(nums: &[isize]) -> Vec<isize> {
-    nums.iter().map_windows(|[&a, &b]| b - a).collect()
+#[derive(Debug, PartialEq, Eq)]
+enum FitError {
+    Overflow,
+    // The differences never became all zero
+    NotPolynomial { levels: usize },
+    // A recurrence fitted the sequence, but the value it gives isn't a whole number
+    NonIntegerPrediction { prediction: Fraction },
 }
 
-fn predict_next(nums: &[isize]) -> isize {
-    if nums.iter().all(|&n| n == 0) {
-        0
-    } else {
-        nums.last().unwrap() + predict_next(&derivatives(nums))
+impl fmt::Display for FitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FitError::Overflow => write!(f, "arithmetic overflowed"),
+            FitError::NotPolynomial { levels } => {
+                write!(f, "differences did not reach zero after {} levels", levels)
+            }
+            FitError::NonIntegerPrediction { prediction } => {
+                write!(f, "predicted {}/{}, which is not a whole number", prediction.num, prediction.den)
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct LineError {
+    line: usize,
+    error: FitError,
+}
+
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.error)
     }
 }
 
-fn predict_prev(nums: &[isize]) -> isize {
-    if nums.iter().all(|&n| n == 0) {
-        0
+// An exact fraction, kept in lowest terms with a positive denominator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Fraction {
+    num: i128,
+    den: i128,
+}
+
+impl Fraction {
+    fn new(num: i128, den: i128) -> Option<Fraction> {
+        if den == 0 {
+            return None;
+        }
+        let g = gcd(num.unsigned_abs(), den.unsigned_abs()).max(1) as i128;
+        let sign = den.signum();
+        Some(Fraction { num: sign.checked_mul(num / g)?, den: sign.checked_mul(den / g)? })
+    }
+
+    fn integer(n: i128) -> Fraction {
+        Fraction { num: n, den: 1 }
+    }
+
+    fn to_integer(self) -> Option<i128> {
+        (self.den == 1).then_some(self.num)
+    }
+
+    fn checked_add(self, other: Fraction) -> Option<Fraction> {
+        let num = self.num.checked_mul(other.den)?.checked_add(other.num.checked_mul(self.den)?)?;
+        Fraction::new(num, self.den.checked_mul(other.den)?)
+    }
+
+    fn checked_sub(self, other: Fraction) -> Option<Fraction> {
+        self.checked_add(Fraction { num: other.num.checked_neg()?, den: other.den })
+    }
+
+    fn checked_mul(self, other: Fraction) -> Option<Fraction> {
+        // Cross-cancel first to keep the intermediate products small
+        let a = Fraction::new(self.num, other.den)?;
+        let b = Fraction::new(other.num, self.den)?;
+        Fraction::new(a.num.checked_mul(b.num)?, a.den.checked_mul(b.den)?)
+    }
+
+    fn checked_div(self, other: Fraction) -> Option<Fraction> {
+        self.checked_mul(Fraction::new(other.den, other.num)?)
+    }
+}
+
+fn gcd(a: u128, b: u128) -> u128 {
+    if b == 0 {
+        a
     } else {
-        nums.first().unwrap() - predict_prev(&derivatives(nums))
+        gcd(b, a % b)
     }
 }
 
-fn part1(input: &str) -> isize {
-    let nums = parse(input);
-    nums.iter().map(|nums| predict_next(nums)).sum()
+// The minimal degree polynomial through a sequence, in Newton forward difference form:
+// f(x) = sum over k of coefficients[k] * (x choose k), where coefficients[k] is the kth difference at x = 0
+#[derive(Debug, PartialEq, Eq)]
+struct Polynomial {
+    coefficients: Vec<i128>,
 }
 
-fn part2(input: &str) -> isize {
-    let nums = parse(input);
-    nums.iter().map(|nums| predict_prev(nums)).sum()
+impl Polynomial {
+    fn fit(nums: &[i128]) -> Result<Polynomial, FitError> {
+        // Difference the sequence in place, one level at a time, keeping the first value of each level
+        let mut diffs = nums.to_vec();
+        let mut coefficients = Vec::new();
+        let levels = diffs.len();
+        for len in (1..=levels).rev() {
+            if diffs[..len].iter().all(|&n| n == 0) {
+                if coefficients.is_empty() {
+                    coefficients.push(0);
+                }
+                return Ok(Polynomial { coefficients });
+            }
+            coefficients.push(diffs[0]);
+            for i in 0..len - 1 {
+                diffs[i] = diffs[i + 1].checked_sub(diffs[i]).ok_or(FitError::Overflow)?;
+            }
+        }
+        Err(FitError::NotPolynomial { levels })
+    }
+
+    fn degree(&self) -> usize {
+        self.coefficients.len() - 1
+    }
+
+    // The value at any (possibly negative) index, or None if it overflows
+    fn evaluate(&self, x: i128) -> Option<i128> {
+        let mut total = 0_i128;
+        // x choose k, built up incrementally; (x choose k-1) * (x - k + 1) is always divisible by k
+        let mut binomial = 1_i128;
+        for (k, coefficient) in self.coefficients.iter().enumerate() {
+            if k > 0 {
+                binomial = binomial.checked_mul(x.checked_sub(k as i128 - 1)?)? / k as i128;
+            }
+            total = total.checked_add(coefficient.checked_mul(binomial)?)?;
+        }
+        Some(total)
+    }
+
+    // The value at a fractional index
+    fn evaluate_fraction(&self, x: Fraction) -> Option<Fraction> {
+        let mut total = Fraction::integer(0);
+        let mut binomial = Fraction::integer(1);
+        for (k, coefficient) in self.coefficients.iter().enumerate() {
+            if k > 0 {
+                let factor = x.checked_sub(Fraction::integer(k as i128 - 1))?.checked_mul(Fraction::new(1, k as i128)?)?;
+                binomial = binomial.checked_mul(factor)?;
+            }
+            total = total.checked_add(binomial.checked_mul(Fraction::integer(*coefficient))?)?;
+        }
+        Some(total)
+    }
+}
+
+// A linear recurrence a[n] = coefficients[0] * a[n - 1] + ... + coefficients[k - 1] * a[n - k]
+#[derive(Debug, PartialEq, Eq)]
+struct Recurrence {
+    coefficients: Vec<Fraction>,
+}
+
+impl Recurrence {
+    // The lowest order recurrence that holds across the whole sequence, checked against at least one
+    // term beyond those used to solve for it
+    fn find(nums: &[i128]) -> Option<Recurrence> {
+        let nums = nums.iter().map(|&n| Fraction::integer(n)).collect::<Vec<_>>();
+        (1..=nums.len().saturating_sub(1) / 2).find_map(|order| {
+            let equations = (order..2 * order)
+                .map(|n| (1..=order).map(|i| nums[n - i]).chain([nums[n]]).collect())
+                .collect();
+            let recurrence = Recurrence { coefficients: solve(equations)? };
+            (2 * order..nums.len())
+                .all(|n| recurrence.next(&nums[..n]) == Some(nums[n]))
+                .then_some(recurrence)
+        })
+    }
+
+    fn next(&self, nums: &[Fraction]) -> Option<Fraction> {
+        self.coefficients.iter().zip(nums.iter().rev()).try_fold(Fraction::integer(0), |total, (&c, &n)| {
+            total.checked_add(c.checked_mul(n)?)
+        })
+    }
+
+    // Runs the recurrence backwards, which needs the last coefficient to be nonzero
+    fn prev(&self, nums: &[Fraction]) -> Option<Fraction> {
+        let (&last, rest) = self.coefficients.split_last()?;
+        let order = self.coefficients.len();
+        let partial = rest.iter().zip(nums[..order - 1].iter().rev()).try_fold(Fraction::integer(0), |total, (&c, &n)| {
+            total.checked_add(c.checked_mul(n)?)
+        })?;
+        nums[order - 1].checked_sub(partial)?.checked_div(last)
+    }
+}
+
+// Gauss-Jordan elimination on rows of [coefficients.., constant], or None if the system is singular
+fn solve(mut rows: Vec<Vec<Fraction>>) -> Option<Vec<Fraction>> {
+    let size = rows.len();
+    for col in 0..size {
+        let pivot = (col..size).find(|&row| rows[row][col].num != 0)?;
+        rows.swap(col, pivot);
+        let pivot_row = rows[col].clone();
+        for (row, values) in rows.iter_mut().enumerate() {
+            if row == col || values[col].num == 0 {
+                continue;
+            }
+            let factor = values[col].checked_div(pivot_row[col])?;
+            for (value, &pivot_value) in values.iter_mut().zip(&pivot_row) {
+                *value = value.checked_sub(factor.checked_mul(pivot_value)?)?;
+            }
+        }
+    }
+    rows.iter().enumerate().map(|(i, row)| row[size].checked_div(row[i])).collect()
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Direction {
+    Next,
+    Prev,
+}
+
+fn predict(nums: &[i128], direction: Direction, fallback: bool) -> Result<i128, FitError> {
+    let polynomial = match Polynomial::fit(nums) {
+        Ok(polynomial) => polynomial,
+        Err(error @ FitError::NotPolynomial { .. }) if fallback => {
+            // If no recurrence fits, or it can't be run, report the original failure
+            let Some(recurrence) = Recurrence::find(nums) else {
+                return Err(error);
+            };
+            let nums = nums.iter().map(|&n| Fraction::integer(n)).collect::<Vec<_>>();
+            let prediction = match direction {
+                Direction::Next => recurrence.next(&nums),
+                Direction::Prev => recurrence.prev(&nums),
+            };
+            let prediction = prediction.ok_or(error)?;
+            return prediction.to_integer().ok_or(FitError::NonIntegerPrediction { prediction });
+        }
+        Err(error) => return Err(error),
+    };
+    let x = match direction {
+        Direction::Next => nums.len() as i128,
+        Direction::Prev => -1,
+    };
+    polynomial.evaluate(x).ok_or(FitError::Overflow)
+}
+
+#[derive(Debug)]
+struct Summary {
+    total: i128,
+    failures: Vec<LineError>,
+}
+
+// Sums the predictions for every line that can be extrapolated, collecting the lines that can't
+fn predict_all(input: &str, direction: Direction, fallback: bool) -> Summary {
+    let mut summary = Summary { total: 0, failures: Vec::new() };
+    for (i, nums) in parse(input).iter().enumerate() {
+        match predict(nums, direction, fallback).and_then(|n| summary.total.checked_add(n).ok_or(FitError::Overflow)) {
+            Ok(total) => summary.total = total,
+            Err(error) => summary.failures.push(LineError { line: i + 1, error }),
+        }
+    }
+    summary
+}
+
+fn part1(input: &str, fallback: bool) -> Summary {
+    predict_all(input, Direction::Next, fallback)
+}
+
+fn part2(input: &str, fallback: bool) -> Summary {
+    predict_all(input, Direction::Prev, fallback)
+}
+
+// Evaluates every line's polynomial at an index such as "1000", "-5" or "21/2"
+fn extrapolate(input: &str, index: &str) {
+    let x = match index.split_once('/') {
+        Some((num, den)) => Fraction::new(num.parse().unwrap(), den.parse().unwrap()),
+        None => Some(Fraction::integer(index.parse().unwrap())),
+    }.expect("Invalid index");
+    let mut total = Some(Fraction::integer(0));
+    for (i, nums) in parse(input).iter().enumerate() {
+        let polynomial = match Polynomial::fit(nums) {
+            Ok(polynomial) => polynomial,
+            Err(error) => {
+                println!("line {}: {}", i + 1, error);
+                continue;
+            }
+        };
+        let value = polynomial.evaluate_fraction(x);
+        match value {
+            Some(value) => println!("line {} (degree {}): {}/{}", i + 1, polynomial.degree(), value.num, value.den),
+            None => println!("line {} (degree {}): overflowed", i + 1, polynomial.degree()),
+        }
+        total = total.zip(value).and_then(|(total, value)| total.checked_add(value));
+    }
+    match total {
+        Some(total) => println!("total: {}/{}", total.num, total.den),
+        None => println!("total: overflowed"),
+    }
 }
 
 fn main() {
     let input = include_str!("../../input/day09");
-    println!("part1: {}", part1(input));
-    println!("part2: {}", part2(input));
+    let args = std::env::args().collect::<Vec<_>>();
+    if let Some(pos) = args.iter().position(|arg| arg == "--at") {
+        extrapolate(input, args.get(pos + 1).expect("--at needs an index"));
+        return;
+    }
+    let fallback = args.iter().any(|arg| arg == "--fallback");
+    for (name, summary) in [("part1", part1(input, fallback)), ("part2", part2(input, fallback))] {
+        println!("{}: {}", name, summary.total);
+        for failure in &summary.failures {
+            eprintln!("skipped {}", failure);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -52,11 +326,68 @@ mod tests {
 
     #[test]
     fn test_part1() {
-        assert_eq!(part1(EXAMPLE), 114);
+        assert_eq!(part1(EXAMPLE, false).total, 114);
     }
 
     #[test]
     fn test_part2() {
-        assert_eq!(part2(EXAMPLE), 2);
+        assert_eq!(part2(EXAMPLE, false).total, 2);
+    }
+
+    #[test]
+    fn test_fit_degree_and_evaluate() {
+        let triangular = Polynomial::fit(&[1, 3, 6, 10, 15, 21]).unwrap();
+        assert_eq!(triangular.degree(), 2);
+        // The nth value is (n + 1)(n + 2) / 2
+        assert_eq!(triangular.evaluate(1_000_000), Some(500_001_500_001));
+        assert_eq!(triangular.evaluate(-3), Some(1));
+        assert_eq!(triangular.evaluate_fraction(Fraction::new(1, 2).unwrap()), Fraction::new(15, 8));
+
+        assert_eq!(Polynomial::fit(&[7, 7, 7]).unwrap().degree(), 0);
+        assert_eq!(Polynomial::fit(&[0, 0]).unwrap().evaluate(5), Some(0));
+        assert_eq!(Polynomial::fit(&[1, 2, 4, 8]), Err(FitError::NotPolynomial { levels: 4 }));
+    }
+
+    #[test]
+    fn test_overflow_is_detected() {
+        let cubes = (0..6_i128).map(|n| n * n * n).collect::<Vec<_>>();
+        let polynomial = Polynomial::fit(&cubes).unwrap();
+        assert_eq!(polynomial.evaluate(1_000_000), Some(1_000_000_000_000_000_000));
+        assert_eq!(polynomial.evaluate(10_i128.pow(13)), None);
+        assert_eq!(Polynomial::fit(&[i128::MIN, i128::MAX]), Err(FitError::Overflow));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_non_polynomial_lines() {
+        let input = format!("{}\n1 1 2 3 5 8 13 21\n1 5 2 8 3", EXAMPLE);
+
+        let summary = part1(&input, false);
+        assert_eq!(summary.total, 114);
+        assert_eq!(summary.failures, vec![
+            LineError { line: 4, error: FitError::NotPolynomial { levels: 8 } },
+            LineError { line: 5, error: FitError::NotPolynomial { levels: 5 } },
+        ]);
+        assert_eq!(summary.failures[0].to_string(), "line 4: differences did not reach zero after 8 levels");
+
+        // The Fibonacci line continues as a recurrence, the last line has no pattern at all
+        let summary = part1(&input, true);
+        assert_eq!(summary.total, 114 + 34);
+        assert_eq!(summary.failures.iter().map(|f| f.line).collect::<Vec<_>>(), vec![5]);
+        let summary = part2(&input, true);
+        assert_eq!(summary.total, 2);
+        assert_eq!(summary.failures.len(), 1);
+    }
+
+    #[test]
+    fn test_recurrence() {
+        let recurrence = Recurrence::find(&[1, 2, 4, 8, 16]).unwrap();
+        assert_eq!(recurrence.coefficients, vec![Fraction::integer(2)]);
+        // Halving 1 doesn't give a whole number
+        assert_eq!(predict(&[1, 2, 4, 8, 16], Direction::Next, true), Ok(32));
+        let error = predict(&[1, 2, 4, 8, 16], Direction::Prev, true).unwrap_err();
+        assert_eq!(error, FitError::NonIntegerPrediction { prediction: Fraction::new(1, 2).unwrap() });
+        assert_eq!(error.to_string(), "predicted 1/2, which is not a whole number");
+        assert_eq!(predict(&[2, 4, 8, 16], Direction::Prev, true), Ok(1));
+        assert_eq!(Recurrence::find(&[2, 3, 5, 7, 11, 13, 17, 19]), None);
+    }
+}
@@ -1,4 +1,7 @@
 use std::collections::HashSet;
+use std::fmt;
+
+use aoc23::polygon;
 
 type Coord = (usize, usize);
 type Direction = (i8, i8);
@@ -30,20 +33,121 @@ impl DirectionExt for Direction {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tile {
+    Loop,
+    Inside,
+    Outside,
+}
+
+// The two directions a pipe connects, or None if the byte isn't a pipe
+fn pipe_ends(byte: u8) -> Option<[Direction; 2]> {
+    match byte {
+        b'|' => Some([NORTH, SOUTH]),
+        b'-' => Some([EAST, WEST]),
+        b'L' => Some([NORTH, EAST]),
+        b'J' => Some([NORTH, WEST]),
+        b'7' => Some([SOUTH, WEST]),
+        b'F' => Some([SOUTH, EAST]),
+        _ => None,
+    }
+}
+
+fn direction_name(direction: &Direction) -> &'static str {
+    match *direction {
+        NORTH => "north",
+        SOUTH => "south",
+        EAST => "east",
+        WEST => "west",
+        _ => panic!("Invalid direction: {:?}", direction),
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum PipeIssue {
+    // A pipe end that leads off the map or into a tile that doesn't connect back
+    DeadEnd { coord: Coord, direction: Direction },
+    // A tile that isn't a pipe, ground or the start
+    Broken { coord: Coord, byte: u8 },
+}
+
+impl fmt::Display for PipeIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PipeIssue::DeadEnd { coord, direction } => {
+                write!(f, "dead end at {:?} heading {}", coord, direction_name(direction))
+            }
+            PipeIssue::Broken { coord, byte } => write!(f, "broken pipe '{}' at {:?}", *byte as char, coord),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PipeLoop {
+    path: Vec<Coord>,
+}
+
+impl PipeLoop {
+    fn length(&self) -> usize {
+        self.path.len()
+    }
+
+    fn contains(&self, coord: &Coord) -> bool {
+        self.path.contains(coord)
+    }
+
+    fn enclosed_tiles(&self) -> usize {
+        let vertices = self.path.iter()
+            .map(|&(x, y)| (x as i64, y as i64))
+            .collect::<Vec<_>>();
+        polygon::interior_points(&vertices) as usize
+    }
+}
+
 type Input = &'static [u8];
 struct Map {
     bytes: Input,
     width: usize,
     height: usize,
-    start: Coord,
+    // Where S is, if the map has one
+    start: Option<Coord>,
+    // The two directions S connects in, or none if it isn't on a loop or there's no S
+    start_ends: Vec<Direction>,
 }
 
 impl Map {
     fn new(bytes: Input) -> Self {
         let width = bytes.iter().position(|&b| b == b'\n').unwrap();
-        let height = bytes.len() / (width + 1);
-        let start = bytes.iter().position(|&b| b == b'S').unwrap();
-        Self { bytes, width, height, start: (start % (width + 1), start / width) }
+        let height = bytes.split(|&b| b == b'\n').filter(|line| !line.is_empty()).count();
+        let start = bytes.iter().position(|&b| b == b'S').map(|start| (start % (width + 1), start / (width + 1)));
+        let mut map = Self { bytes, width, height, start, start_ends: Vec::new() };
+        if let Some(start) = start {
+            // Until we know better, S might connect in any direction
+            map.start_ends = vec![NORTH, SOUTH, EAST, WEST];
+            map.start_ends = map.find_start_ends(start);
+        }
+        map
+    }
+
+    // S may have more than two neighbours pointing into it, so follow each one to find the pair that
+    // leads round a loop and back to S
+    fn find_start_ends(&self, start: Coord) -> Vec<Direction> {
+        for first_dir in self.connected_ends(&start) {
+            let mut current_dir = first_dir;
+            let mut current_coord = start.step(&current_dir);
+            for _ in 0..self.width * self.height {
+                if current_coord == start {
+                    return vec![first_dir, current_dir.opposite()];
+                }
+                let ends = self.connected_ends(&current_coord);
+                if ends.len() != 2 {
+                    break;
+                }
+                current_dir = *ends.iter().find(|&&dir| !dir.is_opposite(&current_dir)).unwrap();
+                current_coord = current_coord.step(&current_dir);
+            }
+        }
+        Vec::new()
     }
 
     // Find how many steps it takes to get from the start, S, back to the start.
@@ -53,36 +157,45 @@ impl Map {
     }
 
     fn count_inside_loop(&self) -> usize {
+        self.classify().iter().flatten().filter(|&&tile| tile == Tile::Inside).count()
+    }
+
+    // The pipe hidden under S, deduced from which neighbours connect back to it
+    fn start_pipe(&self) -> u8 {
+        let connects = |dir| self.start_ends.contains(&dir);
+        match (connects(NORTH), connects(SOUTH), connects(EAST), connects(WEST)) {
+            (true, true, _, _) => b'|',
+            (_, _, true, true) => b'-',
+            (true, _, true, _) => b'L',
+            (true, _, _, true) => b'J',
+            (_, true, _, true) => b'7',
+            (_, true, true, _) => b'F',
+            _ => panic!("Invalid start coord: {:?}", self.start),
+        }
+    }
+
+    // Like get, but with S replaced by its real pipe
+    fn pipe(&self, coord: &Coord) -> u8 {
+        match self.get(coord) {
+            b'S' if self.start == Some(*coord) => self.start_pipe(),
+            b => b,
+        }
+    }
+
+    // Sort every tile into the loop, inside it or outside it. Scanning each row, the loop is crossed by
+    // a | or by a run of - between corners that turn opposite ways (L...7 or F...J).
+    fn classify(&self) -> Vec<Vec<Tile>> {
         let loop_coords = self.loop_definition();
-        let mut count_inside_loop = 0;
+        let mut tiles = Vec::with_capacity(self.height);
         let mut horiz_incoming_dir = None;
         for y in 0..self.height {
             let mut num_crossings_for_row = 0;
+            let mut row = Vec::with_capacity(self.width);
             for x in 0..self.width {
                 let coord = (x, y);
                 if loop_coords.contains(&coord) {
-                    let map_char = self.get(&coord);
-                    let map_char = if map_char == b'S' {
-                        let available_moves = self.available_moves(&coord);
-                        if available_moves.contains(&NORTH) && available_moves.contains(&SOUTH) {
-                            b'|'
-                        } else if available_moves.contains(&EAST) && available_moves.contains(&WEST) {
-                            b'-'
-                        } else if available_moves.contains(&NORTH) && available_moves.contains(&EAST) {
-                            b'L'
-                        } else if available_moves.contains(&NORTH) && available_moves.contains(&WEST) {
-                            b'J'
-                        } else if available_moves.contains(&SOUTH) && available_moves.contains(&WEST) {
-                            b'7'
-                        } else if available_moves.contains(&SOUTH) && available_moves.contains(&EAST) {
-                            b'F'
-                        } else {
-                            panic!("Invalid start coord: {:?}", coord);
-                        }
-                    } else {
-                        map_char
-                    };
-                    match map_char {
+                    row.push(Tile::Loop);
+                    match self.pipe(&coord) {
                         b'|' => {
                             num_crossings_for_row += 1;
                         },
@@ -107,57 +220,144 @@ impl Map {
                         _ => {}
                     }
                 } else if num_crossings_for_row % 2 == 1 {
-                    count_inside_loop += 1;
+                    row.push(Tile::Inside);
+                } else {
+                    row.push(Tile::Outside);
                 }
             }
+            tiles.push(row);
         }
-        count_inside_loop
+        tiles
     }
 
-    fn loop_definition(&self) -> HashSet<Coord> {
-        let mut current_dir = self.available_moves(&self.start)[0];
-        let mut current_coord = self.start.step(&current_dir);
-        let mut loop_coords = HashSet::new();
-        loop_coords.insert(self.start);
-        while self.get(&current_coord) != b'S' {
-            loop_coords.insert(current_coord);
-            current_dir = *self.available_moves(&current_coord).iter()
-                .find(|&&dir| !dir.is_opposite(&current_dir)).unwrap();
-            current_coord = current_coord.step(&current_dir);
+    // Draws the loop with box-drawing characters, shading the tiles inside it and blanking those outside
+    fn render(&self) -> String {
+        let mut rendered = String::new();
+        for (y, row) in self.classify().iter().enumerate() {
+            for (x, tile) in row.iter().enumerate() {
+                rendered.push(match tile {
+                    Tile::Loop => match self.pipe(&(x, y)) {
+                        b'|' => '│',
+                        b'-' => '─',
+                        b'L' => '└',
+                        b'J' => '┘',
+                        b'7' => '┐',
+                        b'F' => '┌',
+                        b => panic!("Invalid loop byte {} at {:?}", b as char, (x, y)),
+                    },
+                    Tile::Inside => '▓',
+                    Tile::Outside => ' ',
+                });
+            }
+            rendered.push('\n');
         }
-        loop_coords
+        rendered
     }
 
-    fn available_moves(&self, coord: &Coord) -> Vec<Direction> {
-        match self.get(coord) {
-            b'.' => vec![],
-            b'S' => {
-                vec![NORTH, SOUTH, EAST, WEST].into_iter()
-                    .filter(|dir| self.is_step_in_bounds(coord, dir))
-                    .filter(|dir| self.available_moves(&coord.step(dir)).contains(&dir.opposite()))
-                    .collect()
-            },
-            b => {
-                match b {
-                    b'|' => [NORTH, SOUTH],
-                    b'-' => [EAST, WEST],
-                    b'L' => [NORTH, EAST],
-                    b'J' => [NORTH, WEST],
-                    b'7' => [SOUTH, WEST],
-                    b'F' => [SOUTH, EAST],
-                    _ => panic!("Invalid byte at {:?}", coord),
-                }.into_iter()
-                .filter(|dir| self.is_step_in_bounds(coord, dir))
-                .collect()
+    // Alternative to the crossing count: treat the centre of every loop tile as a polygon vertex, then
+    // Pick's theorem gives the number of tiles strictly inside from the area and the loop length.
+    fn count_inside_loop_by_area(&self) -> usize {
+        PipeLoop { path: self.loop_path() }.enclosed_tiles()
+    }
+
+    // Every closed loop of pipes on the map, whether or not it passes through S. Each loop starts from
+    // its first tile in reading order.
+    fn find_loops(&self) -> Vec<PipeLoop> {
+        let mut visited = HashSet::new();
+        let mut loops = Vec::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let first = (x, y);
+                let ends = self.connected_ends(&first);
+                if ends.len() != 2 || !visited.insert(first) {
+                    continue;
+                }
+                let mut path = vec![first];
+                let mut current_dir = ends[0];
+                let mut current_coord = first.step(&current_dir);
+                let is_closed = loop {
+                    if current_coord == first {
+                        break true;
+                    }
+                    let ends = self.connected_ends(&current_coord);
+                    if ends.len() != 2 || !visited.insert(current_coord) {
+                        break false;
+                    }
+                    path.push(current_coord);
+                    current_dir = *ends.iter().find(|&&dir| !dir.is_opposite(&current_dir)).unwrap();
+                    current_coord = current_coord.step(&current_dir);
+                };
+                if is_closed {
+                    loops.push(PipeLoop { path });
+                }
             }
         }
+        loops
+    }
+
+    // Every pipe end that goes nowhere and every tile that can't be read
+    fn find_issues(&self) -> Vec<PipeIssue> {
+        let mut issues = Vec::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let coord = (x, y);
+                match self.get(&coord) {
+                    b'.' | b'S' => {}
+                    byte => match pipe_ends(byte) {
+                        Some(ends) => {
+                            let connected = self.connected_ends(&coord);
+                            issues.extend(ends.into_iter()
+                                .filter(|dir| !connected.contains(dir))
+                                .map(|direction| PipeIssue::DeadEnd { coord, direction }));
+                        }
+                        None => issues.push(PipeIssue::Broken { coord, byte }),
+                    },
+                }
+            }
+        }
+        issues
+    }
+
+    // The directions a tile's pipe points in, staying on the map. Broken tiles point nowhere.
+    fn ends(&self, coord: &Coord) -> Vec<Direction> {
+        let ends = match self.get(coord) {
+            b'S' if self.start == Some(*coord) => self.start_ends.clone(),
+            byte => pipe_ends(byte).map(Vec::from).unwrap_or_default(),
+        };
+        ends.into_iter().filter(|dir| self.is_step_in_bounds(coord, dir)).collect()
+    }
+
+    // The directions from a tile that lead into a neighbour connecting back to it
+    fn connected_ends(&self, coord: &Coord) -> Vec<Direction> {
+        self.ends(coord).into_iter()
+            .filter(|dir| self.ends(&coord.step(dir)).contains(&dir.opposite()))
+            .collect()
+    }
+
+    fn loop_definition(&self) -> HashSet<Coord> {
+        self.loop_path().into_iter().collect()
+    }
+
+    // The loop tiles in order, starting from S
+    fn loop_path(&self) -> Vec<Coord> {
+        let start = self.start.expect("No S on the map");
+        let mut current_dir = *self.start_ends.first().expect("S is not on a loop");
+        let mut current_coord = start.step(&current_dir);
+        let mut loop_path = vec![start];
+        while current_coord != start {
+            loop_path.push(current_coord);
+            current_dir = *self.connected_ends(&current_coord).iter()
+                .find(|&&dir| !dir.is_opposite(&current_dir)).unwrap();
+            current_coord = current_coord.step(&current_dir);
+        }
+        loop_path
     }
 
     fn is_step_in_bounds(&self, coord: &Coord, direction: &Direction) -> bool {
         (direction.0 >= 0 || coord.0 > 0) &&
-            (direction.0 <= 0 || coord.0 < self.width) &&
+            (direction.0 <= 0 || coord.0 + 1 < self.width) &&
             (direction.1 >= 0 || coord.1 > 0) &&
-            (direction.1 <= 0 || coord.1 < self.height)
+            (direction.1 <= 0 || coord.1 + 1 < self.height)
     }
 
     fn get(&self, coord: &Coord) -> u8 {
@@ -175,10 +375,34 @@ fn part2(input: Input) -> usize {
     map.count_inside_loop()
 }
 
+fn part2_by_area(input: Input) -> usize {
+    let map = Map::new(input);
+    map.count_inside_loop_by_area()
+}
+
 fn main() {
     let input = include_bytes!("../../input/day10");
+    if std::env::args().any(|arg| arg == "--render") {
+        print!("{}", Map::new(input).render());
+        return;
+    }
+    if std::env::args().any(|arg| arg == "--loops") {
+        let map = Map::new(input);
+        for pipe_loop in map.find_loops() {
+            let start = if map.start.is_some_and(|start| pipe_loop.contains(&start)) { ", through S" } else { "" };
+            println!("loop from {:?}: length {}, enclosing {} tiles{}",
+                pipe_loop.path[0], pipe_loop.length(), pipe_loop.enclosed_tiles(), start);
+        }
+        for issue in map.find_issues() {
+            println!("{}", issue);
+        }
+        return;
+    }
     println!("Part 1: {}", part1(input));
     println!("Part 2: {}", part2(input));
+
+    // Alternative solution using the shoelace formula
+    println!("Part 2: {}", part2_by_area(input));
 }
 
 #[cfg(test)]
@@ -212,8 +436,131 @@ LJ...";
 .L--J.L--J.
 ...........";
 
+    const EXAMPLE_4: &str = ".F----7F7F7F7F-7....
+.|F--7||||||||FJ....
+.||.FJ||||||||L7....
+FJL7L7LJLJ||LJ.L-7..
+L--J.L7...LJS7F-7L7.
+....F-J..F7FJ|L7L7L7
+....L7.F7||L7|.L7L7|
+.....|FJLJ|FJ|F7|.LJ
+....FJL-7.||.||||...
+....L---J.LJ.LJLJ...";
+
     #[test]
     fn test_part2() {
         assert_eq!(part2(EXAMPLE_3.as_bytes()), 4);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_loop_path() {
+        let map = Map::new(EXAMPLE_1.as_bytes());
+        let path = map.loop_path();
+        assert_eq!(path.len(), 8);
+        assert_eq!(path[0], (1, 1));
+        // Each tile is one step from the next, including from the last back round to the first
+        for (a, b) in path.iter().zip(path.iter().cycle().skip(1)) {
+            assert_eq!(a.0.abs_diff(b.0) + a.1.abs_diff(b.1), 1);
+        }
+    }
+
+    #[test]
+    fn test_inside_count_methods_agree() {
+        for (example, inside) in [(EXAMPLE_1, 1), (EXAMPLE_2, 1), (EXAMPLE_3, 4), (EXAMPLE_4, 8)] {
+            assert_eq!(part2(example.as_bytes()), inside);
+            assert_eq!(part2_by_area(example.as_bytes()), inside);
+        }
+    }
+
+    #[test]
+    fn test_start_pipe() {
+        assert_eq!(Map::new(EXAMPLE_1.as_bytes()).start_pipe(), b'F');
+        assert_eq!(Map::new(EXAMPLE_2.as_bytes()).start_pipe(), b'F');
+        assert_eq!(Map::new(EXAMPLE_4.as_bytes()).start_pipe(), b'F');
+    }
+
+    #[test]
+    fn test_render() {
+        let map = Map::new(EXAMPLE_3.as_bytes());
+        assert_eq!(map.classify()[6][2], Tile::Inside);
+        assert_eq!(map.classify()[3][4], Tile::Outside);
+        assert_eq!(map.render(), "           
+ ┌───────┐ 
+ │┌─────┐│ 
+ ││     ││ 
+ ││     ││ 
+ │└─┐ ┌─┘│ 
+ │▓▓│ │▓▓│ 
+ └──┘ └──┘ 
+           
+");
+    }
+
+    #[test]
+    fn test_find_loops_and_issues() {
+        let map = Map::new("F7.F-7
+LJ.|.|
+.-.L-J
+.S7X..
+.LJ..|".as_bytes());
+        let loops = map.find_loops();
+        assert_eq!(loops.iter().map(|l| (l.path[0], l.length(), l.enclosed_tiles())).collect::<Vec<_>>(),
+            vec![((0, 0), 4, 0), ((3, 0), 8, 1), ((1, 3), 4, 0)]);
+        assert!(loops[2].contains(&map.start.unwrap()));
+
+        assert_eq!(map.find_issues(), vec![
+            PipeIssue::DeadEnd { coord: (1, 2), direction: EAST },
+            PipeIssue::DeadEnd { coord: (1, 2), direction: WEST },
+            PipeIssue::Broken { coord: (3, 3), byte: b'X' },
+            PipeIssue::DeadEnd { coord: (5, 4), direction: NORTH },
+            PipeIssue::DeadEnd { coord: (5, 4), direction: SOUTH },
+        ]);
+        assert_eq!(map.find_issues()[2].to_string(), "broken pipe 'X' at (3, 3)");
+
+        // Every pipe in this example is part of the loop through S
+        let map = Map::new(EXAMPLE_2.as_bytes());
+        assert_eq!(map.find_loops().iter().map(|l| l.length()).collect::<Vec<_>>(), vec![16]);
+        assert!(map.find_issues().is_empty());
+    }
+
+    #[test]
+    fn test_start_with_stray_neighbours() {
+        // The - left of S points into it, and the X above it is broken, but neither is on the loop
+        let input = ".X...
+-S-7.
+.|.|.
+.L-J.
+.....";
+        let map = Map::new(input.as_bytes());
+        assert_eq!(map.start_pipe(), b'F');
+        assert_eq!(part1(input.as_bytes()), 4);
+        assert_eq!(part2(input.as_bytes()), 1);
+        assert_eq!(part2_by_area(input.as_bytes()), 1);
+
+        let loops = map.find_loops();
+        assert_eq!(loops.len(), 1);
+        assert_eq!(loops[0].length(), 8);
+        assert!(loops[0].contains(&map.start.unwrap()));
+        assert_eq!(map.find_issues(), vec![
+            PipeIssue::Broken { coord: (1, 0), byte: b'X' },
+            PipeIssue::DeadEnd { coord: (0, 1), direction: EAST },
+            PipeIssue::DeadEnd { coord: (0, 1), direction: WEST },
+        ]);
+    }
+
+    #[test]
+    fn test_loops_without_start() {
+        let map = Map::new("F7...
+LJ.F7
+...||
+-..LJ".as_bytes());
+        assert_eq!(map.start, None);
+        assert!(map.start_ends.is_empty());
+        assert_eq!(map.find_loops().iter().map(|l| (l.path[0], l.length())).collect::<Vec<_>>(),
+            vec![((0, 0), 4), ((3, 1), 6)]);
+        assert_eq!(map.find_issues(), vec![
+            PipeIssue::DeadEnd { coord: (0, 3), direction: EAST },
+            PipeIssue::DeadEnd { coord: (0, 3), direction: WEST },
+        ]);
+    }
+}
@@ -5,8 +5,9 @@ struct Coord {
 }
 struct Image {
     galaxies: Vec<Coord>,
-    empty_rows: Vec<usize>,
-    empty_cols: Vec<usize>,
+    // How many galaxies are in each column and row
+    col_counts: Vec<usize>,
+    row_counts: Vec<usize>,
 }
 
 fn parse(input: &str) -> Image {
@@ -18,61 +19,270 @@ fn parse(input: &str) -> Image {
             if c == '#' {
                 galaxies.push(Coord { x: row_index, y: col_index });
             }
-            width = row_index + 1;
+            width = width.max(row_index + 1);
         }
         height = col_index + 1;
     }
 
-    let mut empty_rows = Vec::new();
-    let mut empty_cols = Vec::new();
-    for x in 0..width {
-        if !galaxies.iter().any(|c| c.x == x) {
-            empty_cols.push(x);
-        }
-    }
-    for y in 0..height {
-        if !galaxies.iter().any(|c| c.y == y) {
-            empty_rows.push(y);
-        }
+    let mut col_counts = vec![0; width];
+    let mut row_counts = vec![0; height];
+    for galaxy in &galaxies {
+        col_counts[galaxy.x] += 1;
+        row_counts[galaxy.y] += 1;
     }
-    
+
     Image {
         galaxies,
-        empty_rows,
-        empty_cols,
+        col_counts,
+        row_counts,
     }
 }
 
-fn calc_sum_of_dists(image: &Image, empty_count_as: usize) -> usize {
-    let mut dists_sum = 0;
-    for i in 0..image.galaxies.len() {
-        for j in (i+1)..image.galaxies.len() {
-            let source = &image.galaxies[i];
-            let dest = &image.galaxies[j];
-
-            let left = source.x.min(dest.x);
-            let right = source.x.max(dest.x);
-            let top = source.y.min(dest.y);
-            let bottom = source.y.max(dest.y);
-
-            let num_empty_cols_spanned = image.empty_cols.iter()
-                .filter(|x| **x > left && **x <= right)
-                .count();
-            let num_empty_rows_spanned = image.empty_rows.iter()
-                .filter(|y| **y > top && **y <= bottom)
-                .count();
-            
-            let dist = (right - left) + (bottom - top) + num_empty_cols_spanned * (empty_count_as - 1) + num_empty_rows_spanned * (empty_count_as - 1);
-            dists_sum += dist;
+// Where each column and row of the image ends up after expansion. Each line is weighted by how many
+// lines it counts as, given its index and how many galaxies it holds, and its position is the total
+// weight of the lines before it.
+struct Expansion {
+    col_positions: Vec<u128>,
+    row_positions: Vec<u128>,
+}
+
+impl Expansion {
+    fn new(image: &Image, col_weight: impl Fn(usize, usize) -> u128, row_weight: impl Fn(usize, usize) -> u128) -> Expansion {
+        fn positions(counts: &[usize], weight: impl Fn(usize, usize) -> u128) -> Vec<u128> {
+            counts.iter().enumerate()
+                .scan(0, |position, (index, &count)| {
+                    let start = *position;
+                    *position += weight(index, count);
+                    Some(start)
+                })
+                .collect()
+        }
+        Expansion {
+            col_positions: positions(&image.col_counts, col_weight),
+            row_positions: positions(&image.row_counts, row_weight),
         }
     }
+
+    // Every empty line counts as empty_count_as lines
+    fn uniform(image: &Image, empty_count_as: u128) -> Expansion {
+        Expansion::per_axis(image, empty_count_as, empty_count_as)
+    }
+
+    fn per_axis(image: &Image, empty_col_counts_as: u128, empty_row_counts_as: u128) -> Expansion {
+        Expansion::new(
+            image,
+            |_, count| if count == 0 { empty_col_counts_as } else { 1 },
+            |_, count| if count == 0 { empty_row_counts_as } else { 1 },
+        )
+    }
+
+    // Every line with fewer than sparse_below galaxies counts as sparse_counts_as lines
+    fn by_density(image: &Image, sparse_below: usize, sparse_counts_as: u128) -> Expansion {
+        let weight = |_, count| if count < sparse_below { sparse_counts_as } else { 1 };
+        Expansion::new(image, weight, weight)
+    }
+
+    fn position(&self, coord: &Coord) -> (u128, u128) {
+        (self.col_positions[coord.x], self.row_positions[coord.y])
+    }
+
+    fn dist(&self, source: &Coord, dest: &Coord) -> u128 {
+        let (source, dest) = (self.position(source), self.position(dest));
+        source.0.abs_diff(dest.0) + source.1.abs_diff(dest.1)
+    }
+}
+
+// Once sorted, each position is the larger end of a pair with every position before it, so its
+// contribution to the sum is position * index minus the sum of those earlier positions.
+fn sum_of_pairwise_dists(mut positions: Vec<u128>) -> u128 {
+    positions.sort_unstable();
+    let mut prefix_sum = 0;
+    let mut dists_sum = 0;
+    for (i, position) in positions.iter().enumerate() {
+        dists_sum += position * i as u128 - prefix_sum;
+        prefix_sum += position;
+    }
     dists_sum
 }
 
+// Manhattan distances split into independent x and y sums
+fn calc_sum_of_dists(image: &Image, expansion: &Expansion) -> u128 {
+    let xs = image.galaxies.iter().map(|c| expansion.col_positions[c.x]).collect();
+    let ys = image.galaxies.iter().map(|c| expansion.row_positions[c.y]).collect();
+    sum_of_pairwise_dists(xs) + sum_of_pairwise_dists(ys)
+}
+
+// Galaxies are named by number, starting from 1 in reading order
+fn galaxy_dist(image: &Image, expansion: &Expansion, source: usize, dest: usize) -> Option<u128> {
+    let source = image.galaxies.get(source.checked_sub(1)?)?;
+    let dest = image.galaxies.get(dest.checked_sub(1)?)?;
+    Some(expansion.dist(source, dest))
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Neighbours {
+    // (galaxy number, distance)
+    nearest: (usize, u128),
+    farthest: (usize, u128),
+}
+
+// The nearest and farthest other galaxy to each galaxy, preferring the lowest numbered on ties. The
+// farthest galaxy from any point has the smallest or largest x + y or x - y, so only those four are
+// candidates, but finding the nearest still compares every pair and is O(n²).
+fn find_neighbours(image: &Image, expansion: &Expansion) -> Vec<Option<Neighbours>> {
+    let positions = image.galaxies.iter().map(|galaxy| expansion.position(galaxy)).collect::<Vec<_>>();
+    let dist = |i: usize, j: usize| positions[i].0.abs_diff(positions[j].0) + positions[i].1.abs_diff(positions[j].1);
+
+    // x - y is offset by the largest y so it stays unsigned
+    let bottom = positions.iter().map(|position| position.1).max().unwrap_or(0);
+    let sums = positions.iter().map(|&(x, y)| x + y).collect::<Vec<_>>();
+    let diffs = positions.iter().map(|&(x, y)| x + (bottom - y)).collect::<Vec<_>>();
+    let mut candidates = Vec::new();
+    for keys in [sums, diffs] {
+        candidates.extend(keys.iter().enumerate().min_by_key(|&(j, key)| (key, j)).map(|(j, _)| j));
+        candidates.extend(keys.iter().enumerate().max_by_key(|&(j, key)| (key, std::cmp::Reverse(j))).map(|(j, _)| j));
+    }
+
+    (0..positions.len()).map(|i| {
+        let nearest = (0..positions.len())
+            .filter(|&j| j != i)
+            .map(|j| (j + 1, dist(i, j)))
+            .min_by_key(|&(j, dist)| (dist, j))?;
+        // When every other galaxy sits on top of this one they're all farthest, and the nearest is the
+        // lowest numbered of them
+        let farthest = candidates.iter()
+            .filter(|&&j| j != i)
+            .map(|&j| (j + 1, dist(i, j)))
+            .max_by_key(|&(j, dist)| (dist, std::cmp::Reverse(j)))
+            .filter(|&(_, dist)| dist > 0)
+            .unwrap_or(nearest);
+        Some(Neighbours { nearest, farthest })
+    }).collect()
+}
+
 fn main() {
     let input = include_str!("../../input/day11");
     let image = parse(input);
 
-    println!("Part 1: {}", calc_sum_of_dists(&image, 2));
-    println!("Part 2: {}", calc_sum_of_dists(&image, 1_000_000));
-}
\ No newline at end of file
+    let args = std::env::args().collect::<Vec<_>>();
+    let flag_values = |flag: &str| {
+        let i = args.iter().position(|arg| arg == flag)?;
+        let values = args.get(i + 1..i + 3)
+            .and_then(|values| values.iter().map(|value| value.parse::<u128>().ok()).collect::<Option<Vec<_>>>());
+        if values.is_none() {
+            eprintln!("{} needs two numbers", flag);
+            eprintln!("Usage: day11 [--expand COLS ROWS | --sparse BELOW COUNTS_AS] [--dist FROM TO] [--neighbours]");
+            std::process::exit(1);
+        }
+        values
+    };
+    let expansion = if let Some(factors) = flag_values("--expand") {
+        Some(Expansion::per_axis(&image, factors[0], factors[1]))
+    } else {
+        flag_values("--sparse").map(|values| Expansion::by_density(&image, values[0] as usize, values[1]))
+    };
+    if let Some(expansion) = expansion {
+        println!("Sum: {}", calc_sum_of_dists(&image, &expansion));
+        if let Some(galaxies) = flag_values("--dist") {
+            match galaxy_dist(&image, &expansion, galaxies[0] as usize, galaxies[1] as usize) {
+                Some(dist) => println!("Distance from {} to {}: {}", galaxies[0], galaxies[1], dist),
+                None => println!("No such galaxy"),
+            }
+        }
+        if args.iter().any(|arg| arg == "--neighbours") {
+            for (i, neighbours) in find_neighbours(&image, &expansion).iter().enumerate() {
+                if let Some(Neighbours { nearest, farthest }) = neighbours {
+                    println!("{}: nearest {} ({}), farthest {} ({})", i + 1, nearest.0, nearest.1, farthest.0, farthest.1);
+                }
+            }
+        }
+        return;
+    }
+
+    println!("Part 1: {}", calc_sum_of_dists(&image, &Expansion::uniform(&image, 2)));
+    println!("Part 2: {}", calc_sum_of_dists(&image, &Expansion::uniform(&image, 1_000_000)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "...#......
+.......#..
+#.........
+..........
+......#...
+.#........
+.........#
+..........
+.......#..
+#...#.....";
+
+    #[test]
+    fn test_example() {
+        let image = parse(EXAMPLE);
+        assert_eq!(image.row_counts, vec![1, 1, 1, 0, 1, 1, 1, 0, 1, 2]);
+        assert_eq!(image.col_counts, vec![2, 1, 0, 1, 1, 0, 1, 2, 0, 1]);
+        assert_eq!(calc_sum_of_dists(&image, &Expansion::uniform(&image, 2)), 374);
+        assert_eq!(calc_sum_of_dists(&image, &Expansion::uniform(&image, 10)), 1030);
+        assert_eq!(calc_sum_of_dists(&image, &Expansion::uniform(&image, 100)), 8410);
+    }
+
+    #[test]
+    fn test_matches_pairwise_sum() {
+        let image = parse(EXAMPLE);
+        for empty_count_as in [0, 1, 2, 7, 1_000_000_000_000] {
+            let expansion = Expansion::uniform(&image, empty_count_as);
+            let mut pairwise = 0;
+            for i in 0..image.galaxies.len() {
+                for j in (i + 1)..image.galaxies.len() {
+                    pairwise += expansion.dist(&image.galaxies[i], &image.galaxies[j]);
+                }
+            }
+            assert_eq!(calc_sum_of_dists(&image, &expansion), pairwise);
+        }
+    }
+
+    #[test]
+    fn test_custom_expansion() {
+        let image = parse(EXAMPLE);
+        assert_eq!(galaxy_dist(&image, &Expansion::uniform(&image, 2), 5, 9), Some(9));
+        assert_eq!(galaxy_dist(&image, &Expansion::uniform(&image, 2), 1, 7), Some(15));
+        assert_eq!(galaxy_dist(&image, &Expansion::uniform(&image, 2), 0, 7), None);
+        assert_eq!(galaxy_dist(&image, &Expansion::uniform(&image, 2), 1, 10), None);
+
+        // Galaxy 1 is at (3, 0) and galaxy 7 is at (7, 8), crossing empty column 5 and empty rows 3 and 7
+        let expansion = Expansion::per_axis(&image, 10, 1);
+        assert_eq!(galaxy_dist(&image, &expansion, 1, 7), Some(4 + 9 + 8));
+        let expansion = Expansion::new(&image, |x, _| if x == 5 { 100 } else { 1 }, |y, _| if y == 3 { 3 } else { 1 });
+        assert_eq!(galaxy_dist(&image, &expansion, 1, 7), Some(4 + 99 + 8 + 2));
+
+        // Only the bottom row holds two galaxies, so everything else counts double
+        let sparse = Expansion::by_density(&image, 2, 2);
+        let doubled = Expansion::new(&image, |x, _| if x == 0 || x == 7 { 1 } else { 2 }, |y, _| if y == 9 { 1 } else { 2 });
+        assert_eq!(calc_sum_of_dists(&image, &sparse), calc_sum_of_dists(&image, &doubled));
+    }
+
+    #[test]
+    fn test_neighbours() {
+        let image = parse(EXAMPLE);
+        let neighbours = find_neighbours(&image, &Expansion::uniform(&image, 2));
+        assert_eq!(neighbours.len(), 9);
+        // Galaxy 8 at (0, 9) and galaxy 9 at (4, 9) are 5 apart after column 2 doubles, while galaxy 2 at
+        // (7, 1) is 19 away across two empty columns and two empty rows
+        assert_eq!(neighbours[7], Some(Neighbours { nearest: (9, 5), farthest: (2, 19) }));
+        assert_eq!(find_neighbours(&parse("#"), &Expansion::uniform(&parse("#"), 2)), vec![None]);
+
+        // The farthest galaxy matches comparing every pair, including when everything collapses to one point
+        let expansions = [Expansion::uniform(&image, 1_000_000), Expansion::per_axis(&image, 1, 50), Expansion::new(&image, |_, _| 0, |_, _| 0)];
+        for expansion in expansions {
+            for (i, neighbours) in find_neighbours(&image, &expansion).iter().enumerate() {
+                let farthest = (1..=image.galaxies.len())
+                    .filter(|&j| j != i + 1)
+                    .map(|j| (j, galaxy_dist(&image, &expansion, i + 1, j).unwrap()))
+                    .max_by_key(|&(j, dist)| (dist, std::cmp::Reverse(j)));
+                assert_eq!(neighbours.as_ref().map(|neighbours| neighbours.farthest), farthest);
+            }
+        }
+    }
+}
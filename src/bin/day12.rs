@@ -1,83 +1,6 @@
-use std::collections::HashMap;
-
-struct SpringState<'a> {
-    chars: &'a [char],
-    damanged_lengths: &'a [u8],
-}
-
-impl<'a> SpringState<'a> {
-    fn new(chars: &'a [char], damanged_lengths: &'a [u8]) -> Self {
-        Self {
-            chars,
-            damanged_lengths,
-        }
-    }
-
-    fn count_possible_arrangements(&self, cache: &mut HashMap<(usize, usize), usize>) -> usize {
-        let key = (self.chars.len(), self.damanged_lengths.len());
-        if let Some(&x) = cache.get(&key) {
-            return x;
-        }
-
-        if self.is_complete() {
-            return 1;
-        }
-
-        let mut sum = 0;
-        if let Some(next_state) = self.consume_operational() {
-            sum += next_state.count_possible_arrangements(cache);
-        }
-        if let Some(next_state) = self.consume_damaged() {
-            sum += next_state.count_possible_arrangements(cache);
-        }
-
-        cache.insert(key, sum);
-        sum
-    }
-
-    fn is_complete(&self) -> bool {
-        self.damanged_lengths.is_empty() && self.chars.iter().all(|&c| c == '.')
-    }
-
-    fn consume_operational(&self) -> Option<Self> {
-        if self.chars.is_empty() || !(self.chars[0] == '.' || self.chars[0] == '?') {
-            return None;
-        }
-        Some(Self {
-            chars: &self.chars[1..],
-            damanged_lengths: self.damanged_lengths,
-        })
-    }
-
-    fn consume_damaged(&self) -> Option<Self> {
-        if self.chars.is_empty() || !(self.chars[0] == '#' || self.chars[0] == '?') || self.damanged_lengths.is_empty() {
-            return None;
-        }
-
-        let next_damaged_segments_count = self.damanged_lengths[0];
-
-        if self.chars.len() < next_damaged_segments_count as usize {
-            return None;
-        }
-        if self.chars[0..next_damaged_segments_count as usize].iter().any(|&c| !(c == '#' || c == '?')) {
-            return None;
-        }
-
-        // Character after damaged segment must be operational (. or ?), or end of string
-        match self.chars.get(next_damaged_segments_count as usize) {
-            Some('#') => None,
-            None => Some(Self {
-                chars: &self.chars[(next_damaged_segments_count as usize)..],
-                damanged_lengths: &self.damanged_lengths[1..],
-            }),
-            Some('.') | Some('?') => Some(Self {
-                chars: &self.chars[(next_damaged_segments_count as usize + 1)..],
-                damanged_lengths: &self.damanged_lengths[1..],
-            }),
-            Some(c) => panic!("Unexpected character {}", c),
-        }
-    }
-}
+use aoc23::bignum::BigUint;
+use aoc23::nonogram::{self, Nonogram, Outcome};
+use aoc23::springs::{ArrangementCount, Arrangements, Rng, SpringState};
 
 fn parse_line(line: &str) -> (Vec<char>, Vec<u8>) {
     let (left, right) = line.split_once(' ').unwrap();
@@ -85,43 +8,80 @@ fn parse_line(line: &str) -> (Vec<char>, Vec<u8>) {
 }
 
 fn count_possible_arrangements(line: &str) -> usize {
-    let (chars, damaged_lengths) = parse_line(line);
-    let state = SpringState::new(&chars, &damaged_lengths);
-    let mut cache = HashMap::new();
-    state.count_possible_arrangements(&mut cache)
+    count_possible_arrangements_unfolded(line, 1)
 }
 
-fn count_possible_arrangements_unfolded(line: &str) -> usize {
+// Repeat the full sequence of chars factor times, separating each repetition with a '?', and repeat the
+// damaged lengths the same number of times
+fn unfold(line: &str, factor: usize) -> (Vec<char>, Vec<u8>) {
     let (chars, damaged_lengths) = parse_line(line);
-    
-    // Repeat the full sequence of chars 5 times, separating each repetition with a '?'
-    let mut unfolded_chars = Vec::new();
-    for i in 0..5 {
-        unfolded_chars.extend_from_slice(&chars);
-        if i < 4 {
+    let mut unfolded_chars = Vec::with_capacity((chars.len() + 1) * factor);
+    for i in 0..factor {
+        if i > 0 {
             unfolded_chars.push('?');
         }
+        unfolded_chars.extend_from_slice(&chars);
     }
+    (unfolded_chars, damaged_lengths.repeat(factor))
+}
 
-    // Repeat the full sequence of damaged lengths 5 times
-    let mut unfolded_damaged_lengths = Vec::new();
-    for _ in 0..5 {
-        unfolded_damaged_lengths.extend_from_slice(&damaged_lengths);
-    }
-
+fn count_possible_arrangements_unfolded<T: ArrangementCount>(line: &str, factor: usize) -> T {
+    let (unfolded_chars, unfolded_damaged_lengths) = unfold(line, factor);
     let state = SpringState::new(&unfolded_chars, &unfolded_damaged_lengths);
-    let mut cache = HashMap::new();
-    state.count_possible_arrangements(&mut cache)
+    state.count_possible_arrangements()
 }
 
 fn main() {
     let input = include_str!("../../input/day12");
 
+    // --arrangements N [K] lists every arrangement of line N, or just the Kth. --sample N SEED picks one at random.
+    let args = std::env::args().collect::<Vec<_>>();
+    if let Some(i) = args.iter().position(|arg| arg == "--nonogram") {
+        let puzzle = std::fs::read_to_string(&args[i + 1]).unwrap();
+        match puzzle.parse::<Nonogram>().unwrap_or_else(|err| panic!("{}", err)).solve() {
+            Outcome::Unique(grid) => print!("{}", nonogram::render(&grid)),
+            Outcome::Multiple(grids) => {
+                println!("More than one solution, including:");
+                for grid in grids {
+                    print!("\n{}", nonogram::render(&grid));
+                }
+            }
+            Outcome::Unsolvable(proof) => print!("No solution:\n{}", proof),
+        }
+        return;
+    }
+
+    let flag = args.iter().position(|arg| arg == "--arrangements" || arg == "--sample");
+    if let Some(i) = flag {
+        let line_number = args[i + 1].parse::<usize>().unwrap();
+        let (chars, damaged_lengths) = parse_line(input.lines().nth(line_number - 1).expect("No such line"));
+        let mut arrangements = Arrangements::new(&chars, &damaged_lengths);
+        println!("{} arrangements", arrangements.len());
+        match (args[i].as_str(), args.get(i + 2).map(|arg| arg.parse::<u64>().unwrap())) {
+            ("--sample", Some(seed)) => println!("{}", arrangements.sample(&mut Rng::new(seed)).unwrap_or_default()),
+            ("--sample", None) => panic!("--sample needs a seed"),
+            (_, Some(k)) => println!("{}", arrangements.nth(k as usize).unwrap_or_default()),
+            (_, None) => arrangements.iter().for_each(|arrangement| println!("{}", arrangement)),
+        }
+        return;
+    }
+
     let sum_of_arrangements = input.lines().map(count_possible_arrangements).sum::<usize>();
     println!("Part 1: {}", sum_of_arrangements);
 
-    let sum_of_unfolded_arrangements = input.lines().map(count_possible_arrangements_unfolded).sum::<usize>();
+    let sum_of_unfolded_arrangements = input.lines()
+        .map(|line| count_possible_arrangements_unfolded::<u128>(line, 5))
+        .sum::<u128>();
     println!("Part 2: {}", sum_of_unfolded_arrangements);
+
+    // Any other unfold factor, counted without risk of overflow
+    if let Some(i) = args.iter().position(|arg| arg == "--unfold") {
+        let factor = args[i + 1].parse().unwrap();
+        let sum = input.lines()
+            .map(|line| count_possible_arrangements_unfolded::<BigUint>(line, factor))
+            .fold(BigUint::zero(), |sum, count| sum + count);
+        println!("Unfolded {} times: {}", factor, sum);
+    }
 }
 
 #[cfg(test)]
@@ -140,11 +100,55 @@ mod tests {
 
     #[test]
     fn test_part2() {
-        // assert_eq!(count_possible_arrangements_unfolded("???.### 1,1,3"), 1);
-        // assert_eq!(count_possible_arrangements_unfolded(".??..??...?##. 1,1,3"), 16384);
-        // assert_eq!(count_possible_arrangements_unfolded("?#?#?#?#?#?#?#? 1,3,1,6"), 1);
-        // assert_eq!(count_possible_arrangements_unfolded("????.#...#... 4,1,1"), 16);
-        // assert_eq!(count_possible_arrangements_unfolded("????.######..#####. 1,6,5"), 2500);
-        assert_eq!(count_possible_arrangements_unfolded("?###???????? 3,2,1"), 506250);
+        assert_eq!(count_possible_arrangements_unfolded::<usize>("???.### 1,1,3", 5), 1);
+        assert_eq!(count_possible_arrangements_unfolded::<usize>(".??..??...?##. 1,1,3", 5), 16384);
+        assert_eq!(count_possible_arrangements_unfolded::<usize>("?#?#?#?#?#?#?#? 1,3,1,6", 5), 1);
+        assert_eq!(count_possible_arrangements_unfolded::<usize>("????.#...#... 4,1,1", 5), 16);
+        assert_eq!(count_possible_arrangements_unfolded::<usize>("????.######..#####. 1,6,5", 5), 2500);
+        assert_eq!(count_possible_arrangements_unfolded::<usize>("?###???????? 3,2,1", 5), 506250);
+    }
+
+    #[test]
+    fn test_unfold_factor() {
+        assert_eq!(unfold("#. 1", 3), ("#.?#.?#.".chars().collect(), vec![1, 1, 1]));
+        assert_eq!(count_possible_arrangements_unfolded::<usize>(".??..??...?##. 1,1,3", 1), 4);
+        assert_eq!(count_possible_arrangements_unfolded::<usize>(".??..??...?##. 1,1,3", 0), 1);
+        // Each extra copy of this row multiplies the count by 8
+        assert_eq!(count_possible_arrangements_unfolded::<u128>(".??..??...?##. 1,1,3", 20), 4 * 8_u128.pow(19));
+        let count = count_possible_arrangements_unfolded::<BigUint>(".??..??...?##. 1,1,3", 50);
+        assert_eq!(count, BigUint::from(4_u8) * (BigUint::from(1_u8) << (3 * 49)));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_arrangements() {
+        let (chars, damaged_lengths) = parse_line("?###???????? 3,2,1");
+        let mut arrangements = Arrangements::new(&chars, &damaged_lengths);
+        assert_eq!(arrangements.len(), 10);
+        assert_eq!(arrangements.nth(0).unwrap(), ".###.##.#...");
+        assert_eq!(arrangements.nth(9).unwrap(), ".###....##.#");
+        assert_eq!(arrangements.nth(10), None);
+
+        let all = arrangements.iter().collect::<Vec<_>>();
+        assert!(all.windows(2).all(|pair| pair[0] < pair[1]));
+        for arrangement in &all {
+            // Each arrangement fits the row and has exactly one way to match itself
+            assert!(arrangement.chars().zip(&chars).all(|(a, &c)| c == '?' || a == c));
+            assert_eq!(count_possible_arrangements(&format!("{} 3,2,1", arrangement)), 1);
+        }
+
+        let mut rng = Rng::new(2023);
+        let mut seen = (0..200).map(|_| arrangements.sample(&mut rng).unwrap()).collect::<Vec<_>>();
+        seen.sort();
+        seen.dedup();
+        assert_eq!(seen, all);
+    }
+
+    #[test]
+    fn test_no_arrangements() {
+        let (chars, damaged_lengths) = parse_line("#.# 3");
+        let mut arrangements = Arrangements::new(&chars, &damaged_lengths);
+        assert_eq!(arrangements.len(), 0);
+        assert_eq!(arrangements.iter().next(), None);
+        assert_eq!(arrangements.sample(&mut Rng::new(1)), None);
+    }
+}
@@ -1,4 +1,6 @@
-use std::{collections::{HashMap, VecDeque}, ops::RangeInclusive};
+use std::collections::{HashMap, VecDeque};
+
+use aoc23::intervals::Interval;
 
 #[derive(Clone, PartialEq, Eq, Debug)]
 enum Transition {
@@ -169,42 +171,35 @@ trait LimitableRange where Self: Sized {
     fn split_greater_than(&self, value: u16) -> (Option<Self>, Option<Self>);
 }
 
-impl LimitableRange for RangeInclusive<u16> {
+impl LimitableRange for Interval<u16> {
     fn split_less_than(&self, value: u16) -> (Option<Self>, Option<Self>) {
-        if *self.end() < value {
-            (Some(self.clone()), None)
-        } else if *self.start() >= value {
-            (None, Some(self.clone()))
-        } else {
-            (Some(*self.start()..=value-1), Some(value..=*self.end()))
-        }
+        self.split_at(value)
     }
 
     fn split_greater_than(&self, value: u16) -> (Option<Self>, Option<Self>) {
-        if *self.start() > value {
-            (Some(self.clone()), None)
-        } else if *self.end() <= value {
-            (None, Some(self.clone()))
-        } else {
-            (Some(value+1..=*self.end()), Some(*self.start()..=value))
-        }
+        // Nothing is greater than the largest value
+        let Some(threshold) = value.checked_add(1) else {
+            return (None, Some(*self));
+        };
+        let (fail, pass) = self.split_at(threshold);
+        (pass, fail)
     }
 }
 
 #[derive(Clone, PartialEq, Eq, Debug)]
 struct PartRanges {
-    x: RangeInclusive<u16>,
-    m: RangeInclusive<u16>,
-    a: RangeInclusive<u16>,
-    s: RangeInclusive<u16>,
+    x: Interval<u16>,
+    m: Interval<u16>,
+    a: Interval<u16>,
+    s: Interval<u16>,
 }
 impl PartRanges {
     fn new() -> Self {
         PartRanges {
-            x: 1..=4000,
-            m: 1..=4000,
-            a: 1..=4000,
-            s: 1..=4000,
+            x: (1..=4000).into(),
+            m: (1..=4000).into(),
+            a: (1..=4000).into(),
+            s: (1..=4000).into(),
         }
     }
 
@@ -221,7 +216,7 @@ impl PartRanges {
         )
     }
 
-    fn get(&self, category: &PartCategory) -> &RangeInclusive<u16> {
+    fn get(&self, category: &PartCategory) -> &Interval<u16> {
         match category {
             PartCategory::X => &self.x,
             PartCategory::M => &self.m,
@@ -230,12 +225,12 @@ impl PartRanges {
         }
     }
 
-    fn clone_with(&self, category: &PartCategory, value: RangeInclusive<u16>) -> Self {
+    fn clone_with(&self, category: &PartCategory, value: Interval<u16>) -> Self {
         match category {
-            PartCategory::X => PartRanges { x: value, m: self.m.clone(), a: self.a.clone(), s: self.s.clone() },
-            PartCategory::M => PartRanges { x: self.x.clone(), m: value, a: self.a.clone(), s: self.s.clone() },
-            PartCategory::A => PartRanges { x: self.x.clone(), m: self.m.clone(), a: value, s: self.s.clone() },
-            PartCategory::S => PartRanges { x: self.x.clone(), m: self.m.clone(), a: self.a.clone(), s: value },
+            PartCategory::X => PartRanges { x: value, m: self.m, a: self.a, s: self.s },
+            PartCategory::M => PartRanges { x: self.x, m: value, a: self.a, s: self.s },
+            PartCategory::A => PartRanges { x: self.x, m: self.m, a: value, s: self.s },
+            PartCategory::S => PartRanges { x: self.x, m: self.m, a: self.a, s: value },
         }
     }
 }
@@ -313,7 +308,7 @@ fn sum_all_categories_of_parts_accepted_by_system(system: &System, parts: &[Part
 }
 
 fn calculate_possibilities(accept_ranges: &[PartRanges]) -> usize {
-    accept_ranges.iter().map(|r| r.x.len() * r.m.len() * r.a.len() * r.s.len()).sum()
+    accept_ranges.iter().map(|r| r.x.len() as usize * r.m.len() as usize * r.a.len() as usize * r.s.len() as usize).sum()
 }
 
 fn count_all_possible_valid_parts(system: &System) -> usize {
@@ -369,15 +364,15 @@ hdj{m>838:A,pv}
         let (system, _) = parse_file(EXAMPLE_1);
         let result = system.workflows["in"].restrict_ranges(&PartRanges::new());
         assert_eq!(result, vec![
-            (PartRanges { x: 1..=4000, m: 1..=4000, a: 1..=4000, s: 1..=1350 }, Transition::Workflow("px".to_string())),
-            (PartRanges { x: 1..=4000, m: 1..=4000, a: 1..=4000, s: 1351..=4000 }, Transition::Workflow("qqz".to_string())),
+            (PartRanges { x: (1..=4000).into(), m: (1..=4000).into(), a: (1..=4000).into(), s: (1..=1350).into() }, Transition::Workflow("px".to_string())),
+            (PartRanges { x: (1..=4000).into(), m: (1..=4000).into(), a: (1..=4000).into(), s: (1351..=4000).into() }, Transition::Workflow("qqz".to_string())),
         ]);
     }
 
     #[test]
     fn text_calculating_possibilities_base() {
         let accept_ranges = vec![
-            PartRanges { x: 1..=4000, m: 1..=4000, a: 1..=4000, s: 1..=4000 },
+            PartRanges { x: (1..=4000).into(), m: (1..=4000).into(), a: (1..=4000).into(), s: (1..=4000).into() },
         ];
         assert_eq!(calculate_possibilities(&accept_ranges), 4000 * 4000 * 4000 * 4000);
     }
@@ -385,9 +380,16 @@ hdj{m>838:A,pv}
     #[test]
     fn text_calculating_possibilities_multiple() {
         let accept_ranges = vec![
-            PartRanges { x: 1..=2, m: 1..=3, a: 1..=3, s: 1..=3 }, // 2 * 3 * 3 * 3 = 54
-            PartRanges { x: 3..=3, m: 1..=3, a: 1..=3, s: 1..=3 }, // 1 * 3 * 3 * 3 = 27
+            PartRanges { x: (1..=2).into(), m: (1..=3).into(), a: (1..=3).into(), s: (1..=3).into() }, // 2 * 3 * 3 * 3 = 54
+            PartRanges { x: (3..=3).into(), m: (1..=3).into(), a: (1..=3).into(), s: (1..=3).into() }, // 1 * 3 * 3 * 3 = 27
         ];
         assert_eq!(calculate_possibilities(&accept_ranges), 54 + 27);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_split_greater_than_largest_value() {
+        let range: Interval<u16> = (1..=4000).into();
+        assert_eq!(range.split_greater_than(u16::MAX), (None, Some(range)));
+        assert_eq!(range.split_greater_than(3999), (Some((4000..=4000).into()), Some((1..=3999).into())));
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NeighbourCount {
+    Exactly(usize),
+    AtLeast(usize),
+}
+
+impl NeighbourCount {
+    fn accepts(&self, count: usize) -> bool {
+        match self {
+            NeighbourCount::Exactly(n) => count == *n,
+            NeighbourCount::AtLeast(n) => count >= *n,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Combine {
+    Product,
+    Sum,
+    Max,
+}
+
+impl Combine {
+    fn apply(&self, numbers: &[usize]) -> usize {
+        match self {
+            Combine::Product => numbers.iter().product(),
+            Combine::Sum => numbers.iter().sum(),
+            Combine::Max => numbers.iter().copied().max().unwrap_or(0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Gear {
+    pub symbol: char,
+    pub row_index: usize,
+    pub col_index: usize,
+    pub numbers: Vec<usize>,
+    pub value: usize,
+}
+
+pub struct GearRule {
+    symbols: HashSet<char>,
+    neighbours: NeighbourCount,
+    combine: Combine,
+}
+
+impl GearRule {
+    pub fn new(symbols: &str, neighbours: NeighbourCount, combine: Combine) -> GearRule {
+        GearRule { symbols: symbols.chars().collect(), neighbours, combine }
+    }
+
+    // The rule from the puzzle: a '*' next to exactly two numbers, whose ratio is their product
+    pub fn gear_ratio() -> GearRule {
+        GearRule::new("*", NeighbourCount::Exactly(2), Combine::Product)
+    }
+
+    pub fn applies_to(&self, symbol: char) -> bool {
+        self.symbols.contains(&symbol)
+    }
+
+    pub fn apply(&self, symbol: char, row_index: usize, col_index: usize, numbers: Vec<usize>) -> Option<Gear> {
+        if !self.applies_to(symbol) || !self.neighbours.accepts(numbers.len()) {
+            return None;
+        }
+        let value = self.combine.apply(&numbers);
+        Some(Gear { symbol, row_index, col_index, numbers, value })
+    }
+
+    // Candidates are (symbol, (row_index, col_index), adjacent numbers), in any order
+    pub fn find_gears<I>(&self, candidates: I) -> Vec<Gear>
+        where I: IntoIterator<Item=(char, (usize, usize), Vec<usize>)>
+    {
+        let mut gears = candidates.into_iter()
+            .filter_map(|(symbol, (row_index, col_index), numbers)| self.apply(symbol, row_index, col_index, numbers))
+            .collect::<Vec<_>>();
+        gears.sort_by_key(|gear| (gear.row_index, gear.col_index));
+        gears
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gear_ratio_rule() {
+        let rule = GearRule::gear_ratio();
+        assert_eq!(rule.apply('*', 1, 3, vec![467, 35]).map(|g| g.value), Some(16345));
+        assert_eq!(rule.apply('*', 4, 3, vec![617]), None);
+        assert_eq!(rule.apply('#', 3, 6, vec![633, 592]), None);
+    }
+
+    #[test]
+    fn custom_rule() {
+        let rule = GearRule::new("*#", NeighbourCount::AtLeast(1), Combine::Max);
+        let gears = rule.find_gears(vec![
+            ('#', (3, 6), vec![633]),
+            ('*', (1, 3), vec![467, 35]),
+            ('+', (5, 5), vec![592]),
+        ]);
+        assert_eq!(gears, vec![
+            Gear { symbol: '*', row_index: 1, col_index: 3, numbers: vec![467, 35], value: 467 },
+            Gear { symbol: '#', row_index: 3, col_index: 6, numbers: vec![633], value: 633 },
+        ]);
+
+        let rule = GearRule::new("*", NeighbourCount::Exactly(2), Combine::Sum);
+        assert_eq!(rule.apply('*', 1, 3, vec![467, 35]).map(|g| g.value), Some(502));
+    }
+}
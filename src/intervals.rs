@@ -0,0 +1,374 @@
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::ops::{Add, Range, RangeInclusive, Sub};
+
+pub trait IntervalBound: Copy + Ord + Debug + Add<Output=Self> + Sub<Output=Self> {
+    const ONE: Self;
+}
+
+macro_rules! impl_interval_bound {
+    ($($t:ty),*) => {
+        $(impl IntervalBound for $t {
+            const ONE: Self = 1;
+        })*
+    };
+}
+
+impl_interval_bound!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+// A half-open interval [start, end). Inclusive intervals are converted on construction, so an inclusive
+// interval cannot end at the maximum value of its type.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Interval<T> {
+    pub start: T,
+    pub end: T,
+}
+
+impl<T: IntervalBound> Interval<T> {
+    pub fn new(start: T, end: T) -> Interval<T> {
+        assert!(start <= end, "Interval start {:?} is after end {:?}", start, end);
+        Interval { start, end }
+    }
+
+    pub fn inclusive(start: T, last: T) -> Interval<T> {
+        Interval::new(start, last + T::ONE)
+    }
+
+    pub fn last(&self) -> T {
+        self.end - T::ONE
+    }
+
+    pub fn len(&self) -> T {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    pub fn contains(&self, value: T) -> bool {
+        self.start <= value && value < self.end
+    }
+
+    pub fn intersection(&self, other: &Interval<T>) -> Option<Interval<T>> {
+        let start = self.start.max(other.start);
+        let end = self.end.min(other.end);
+        if start < end {
+            Some(Interval { start, end })
+        } else {
+            None
+        }
+    }
+
+    // Splits into the parts before and from value, either of which may be absent
+    pub fn split_at(&self, value: T) -> (Option<Interval<T>>, Option<Interval<T>>) {
+        if value <= self.start {
+            (None, Some(*self))
+        } else if value >= self.end {
+            (Some(*self), None)
+        } else {
+            (Some(Interval { start: self.start, end: value }), Some(Interval { start: value, end: self.end }))
+        }
+    }
+}
+
+impl<T: IntervalBound> From<Range<T>> for Interval<T> {
+    fn from(range: Range<T>) -> Self {
+        Interval::new(range.start, range.end)
+    }
+}
+
+impl<T: IntervalBound> From<RangeInclusive<T>> for Interval<T> {
+    fn from(range: RangeInclusive<T>) -> Self {
+        Interval::inclusive(*range.start(), *range.end())
+    }
+}
+
+// An ordered set of values, stored as disjoint, non-abutting intervals keyed by their start
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct IntervalSet<T> {
+    intervals: BTreeMap<T, T>,
+}
+
+impl<T: IntervalBound> IntervalSet<T> {
+    pub fn new() -> IntervalSet<T> {
+        IntervalSet { intervals: BTreeMap::new() }
+    }
+
+    pub fn insert(&mut self, interval: Interval<T>) {
+        if interval.is_empty() {
+            return;
+        }
+        let Interval { mut start, mut end } = interval;
+
+        // Absorb the interval starting at or before this one, if it overlaps or abuts
+        if let Some((&prev_start, &prev_end)) = self.intervals.range(..=start).next_back() {
+            if prev_end >= start {
+                start = prev_start;
+                end = end.max(prev_end);
+                self.intervals.remove(&prev_start);
+            }
+        }
+        // Absorb any intervals starting within (or immediately after) this one
+        while let Some((&next_start, &next_end)) = self.intervals.range(start..=end).next() {
+            end = end.max(next_end);
+            self.intervals.remove(&next_start);
+        }
+
+        self.intervals.insert(start, end);
+    }
+
+    pub fn contains(&self, value: T) -> bool {
+        self.intervals.range(..=value).next_back().is_some_and(|(_, &end)| value < end)
+    }
+
+    pub fn len(&self) -> usize {
+        self.intervals.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    pub fn first(&self) -> Option<Interval<T>> {
+        self.intervals.first_key_value().map(|(&start, &end)| Interval { start, end })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=Interval<T>> + '_ {
+        self.intervals.iter().map(|(&start, &end)| Interval { start, end })
+    }
+}
+
+impl<T: IntervalBound> FromIterator<Interval<T>> for IntervalSet<T> {
+    fn from_iter<I: IntoIterator<Item=Interval<T>>>(iter: I) -> Self {
+        let mut set = IntervalSet::new();
+        for interval in iter {
+            set.insert(interval);
+        }
+        set
+    }
+}
+
+// A piecewise map where each source interval is shifted to start at a destination value. Values outside
+// every source interval map to themselves.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct IntervalMap<T> {
+    // source start -> (source end, destination start)
+    pieces: BTreeMap<T, (T, T)>,
+}
+
+impl<T: IntervalBound> IntervalMap<T> {
+    pub fn new() -> IntervalMap<T> {
+        IntervalMap { pieces: BTreeMap::new() }
+    }
+
+    pub fn insert(&mut self, source: Interval<T>, dest_start: T) {
+        if source.is_empty() {
+            return;
+        }
+        let overlaps_prev = self.pieces.range(..=source.start).next_back().is_some_and(|(_, &(end, _))| end > source.start);
+        let overlaps_next = self.pieces.range(source.start..source.end).next().is_some();
+        assert!(!overlaps_prev && !overlaps_next, "Source interval {:?} overlaps an existing piece", source);
+        self.pieces.insert(source.start, (source.end, dest_start));
+    }
+
+    pub fn get(&self, value: T) -> T {
+        match self.pieces.range(..=value).next_back() {
+            Some((&start, &(end, dest_start))) if value < end => dest_start + (value - start),
+            _ => value,
+        }
+    }
+
+    pub fn pieces(&self) -> impl Iterator<Item=(Interval<T>, T)> + '_ {
+        self.pieces.iter().map(|(&start, &(end, dest_start))| (Interval { start, end }, dest_start))
+    }
+
+    // Splits interval at piece boundaries, returning each part of the source with the destination its start
+    // maps to (parts not covered by any piece map to themselves)
+    pub fn segments(&self, interval: Interval<T>) -> Vec<(Interval<T>, T)> {
+        let mut segments = Vec::new();
+        let mut cur = interval.start;
+
+        let first_key = match self.pieces.range(..=interval.start).next_back() {
+            Some((&start, &(end, _))) if end > interval.start => start,
+            _ => interval.start,
+        };
+        for (&start, &(end, dest_start)) in self.pieces.range(first_key..interval.end) {
+            if cur < start {
+                segments.push((Interval { start: cur, end: start }, cur));
+                cur = start;
+            }
+            let seg_end = end.min(interval.end);
+            segments.push((Interval { start: cur, end: seg_end }, dest_start + (cur - start)));
+            cur = seg_end;
+        }
+        if cur < interval.end {
+            segments.push((Interval { start: cur, end: interval.end }, cur));
+        }
+
+        segments
+    }
+
+    pub fn map_interval(&self, interval: Interval<T>) -> Vec<Interval<T>> {
+        self.segments(interval).into_iter()
+            .map(|(source, dest_start)| Interval { start: dest_start, end: dest_start + source.len() })
+            .collect()
+    }
+
+    pub fn map_set(&self, set: &IntervalSet<T>) -> IntervalSet<T> {
+        set.iter().flat_map(|interval| self.map_interval(interval)).collect()
+    }
+
+    // The parts of interval not covered by any piece
+    fn gaps(&self, interval: Interval<T>) -> Vec<Interval<T>> {
+        let mut gaps = Vec::new();
+        let mut cur = interval.start;
+        if let Some((_, &(end, _))) = self.pieces.range(..=interval.start).next_back() {
+            cur = cur.max(end);
+        }
+        for (&start, &(end, _)) in self.pieces.range(interval.start..interval.end) {
+            if cur < start {
+                gaps.push(Interval { start: cur, end: start });
+            }
+            cur = cur.max(end);
+        }
+        if cur < interval.end {
+            gaps.push(Interval { start: cur, end: interval.end });
+        }
+        gaps
+    }
+
+    // The map equivalent to applying self and then next
+    pub fn compose(&self, next: &IntervalMap<T>) -> IntervalMap<T> {
+        let mut composed = IntervalMap::new();
+
+        // Values moved by self are then moved (or not) by next
+        for (source, dest_start) in self.pieces() {
+            let dest = Interval { start: dest_start, end: dest_start + source.len() };
+            for (segment, next_dest_start) in next.segments(dest) {
+                let start = source.start + (segment.start - dest_start);
+                if start != next_dest_start {
+                    composed.pieces.insert(start, (start + segment.len(), next_dest_start));
+                }
+            }
+        }
+        // Values left alone by self are only moved by next
+        for (source, dest_start) in next.pieces() {
+            for gap in self.gaps(source) {
+                composed.pieces.insert(gap.start, (gap.end, dest_start + (gap.start - source.start)));
+            }
+        }
+
+        composed
+    }
+
+    // The inverse map, if this map is a bijection (no two values map to the same destination)
+    pub fn invert(&self) -> Option<IntervalMap<T>> {
+        let mut dest_pieces = self.pieces()
+            .map(|(source, dest_start)| (Interval { start: dest_start, end: dest_start + source.len() }, source.start))
+            .collect::<Vec<_>>();
+        dest_pieces.sort_by_key(|(dest, _)| dest.start);
+        if dest_pieces.windows(2).any(|w| w[0].0.end > w[1].0.start) {
+            return None;
+        }
+
+        // Values outside every piece map to themselves, so the pieces must only permute the values they cover
+        let sources = self.pieces().map(|(source, _)| source).collect::<IntervalSet<_>>();
+        let dests = dest_pieces.iter().map(|(dest, _)| *dest).collect::<IntervalSet<_>>();
+        if sources != dests {
+            return None;
+        }
+
+        let mut inverse = IntervalMap::new();
+        for (dest, source_start) in dest_pieces {
+            inverse.pieces.insert(dest.start, (dest.end, source_start));
+        }
+        Some(inverse)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interval_conversions() {
+        let half_open: Interval<u16> = (1..4001).into();
+        let inclusive: Interval<u16> = (1..=4000).into();
+        assert_eq!(half_open, inclusive);
+        assert_eq!(inclusive.len(), 4000);
+        assert_eq!(inclusive.last(), 4000);
+        assert_eq!(inclusive.split_at(1351), (Some((1..=1350).into()), Some((1351..=4000).into())));
+        assert_eq!(inclusive.split_at(1), (None, Some(inclusive)));
+    }
+
+    #[test]
+    fn set_merges_overlapping_and_abutting() {
+        let mut set = IntervalSet::new();
+        set.insert(Interval::new(10, 20));
+        set.insert(Interval::new(30, 40));
+        set.insert(Interval::new(50, 60));
+        assert_eq!(set.len(), 3);
+
+        set.insert(Interval::new(20, 35));
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![Interval::new(10, 40), Interval::new(50, 60)]);
+
+        set.insert(Interval::new(-5, 100));
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![Interval::new(-5, 100)]);
+        assert!(set.contains(-5));
+        assert!(!set.contains(100));
+    }
+
+    #[test]
+    fn map_splits_intervals() {
+        // seed-to-soil from the day 5 example
+        let mut map = IntervalMap::new();
+        map.insert(Interval::new(98_u64, 100), 50);
+        map.insert(Interval::new(50, 98), 52);
+
+        assert_eq!(map.get(79), 81);
+        assert_eq!(map.get(99), 51);
+        assert_eq!(map.get(13), 13);
+        assert_eq!(map.map_interval(Interval::new(40, 101)), vec![
+            Interval::new(40, 50),
+            Interval::new(52, 100),
+            Interval::new(50, 52),
+            Interval::new(100, 101),
+        ]);
+        let mapped = map.map_set(&[Interval::new(40, 101)].into_iter().collect());
+        assert_eq!(mapped.iter().collect::<Vec<_>>(), vec![Interval::new(40, 101)]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn map_rejects_overlapping_pieces() {
+        let mut map = IntervalMap::new();
+        map.insert(Interval::new(0_u8, 10), 20);
+        map.insert(Interval::new(5, 15), 40);
+    }
+
+    #[test]
+    fn compose_and_invert() {
+        let mut first = IntervalMap::new();
+        first.insert(Interval::new(98_u64, 100), 50);
+        first.insert(Interval::new(50, 98), 52);
+        let mut second = IntervalMap::new();
+        second.insert(Interval::new(15, 52), 0);
+        second.insert(Interval::new(52, 54), 37);
+        second.insert(Interval::new(0, 15), 39);
+
+        let composed = first.compose(&second);
+        for value in 0..120 {
+            assert_eq!(composed.get(value), second.get(first.get(value)), "value {}", value);
+        }
+
+        let inverse = composed.invert().unwrap();
+        for value in 0..120 {
+            assert_eq!(inverse.get(composed.get(value)), value);
+        }
+
+        let mut squash = IntervalMap::new();
+        squash.insert(Interval::new(0_u64, 10), 5);
+        assert_eq!(squash.invert(), None);
+    }
+}
@@ -0,0 +1,6 @@
+pub mod bignum;
+pub mod gears;
+pub mod intervals;
+pub mod nonogram;
+pub mod polygon;
+pub mod springs;
@@ -0,0 +1,339 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::springs::{CountTable, Feasible};
+
+// Cells are '#' when filled, '.' when empty and '?' while unknown, as in the day 12 spring rows
+pub type Grid = Vec<Vec<char>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Line {
+    Row(usize),
+    Column(usize),
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Line::Row(row) => write!(f, "row {}", row + 1),
+            Line::Column(col) => write!(f, "column {}", col + 1),
+        }
+    }
+}
+
+// Why a puzzle has no solution: either a line has no arrangement that fits the cells already known,
+// or a cell leads to a contradiction whether it is filled or left empty
+#[derive(Debug, PartialEq, Eq)]
+pub enum Proof {
+    Contradiction { line: Line, cells: String },
+    Split { cell: (usize, usize), filled: Box<Proof>, empty: Box<Proof> },
+}
+
+impl Proof {
+    fn write_indented(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        match self {
+            Proof::Contradiction { line, cells } => {
+                writeln!(f, "{:indent$}{} cannot be arranged as {}", "", line, cells, indent = indent)
+            }
+            Proof::Split { cell, filled, empty } => {
+                writeln!(f, "{:indent$}if row {} column {} is filled:", "", cell.0 + 1, cell.1 + 1, indent = indent)?;
+                filled.write_indented(f, indent + 2)?;
+                writeln!(f, "{:indent$}if row {} column {} is empty:", "", cell.0 + 1, cell.1 + 1, indent = indent)?;
+                empty.write_indented(f, indent + 2)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Proof {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write_indented(f, 0)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Unique(Grid),
+    // The first two solutions found, since there could be too many to list them all
+    Multiple(Vec<Grid>),
+    Unsolvable(Proof),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nonogram {
+    pub rows: Vec<Vec<u8>>,
+    pub cols: Vec<Vec<u8>>,
+}
+
+// Fills in the unknown cells of a line that are the same in every arrangement, or None if it has no
+// arrangements at all. Only whether each part of the line fits its clues matters, so one table gives
+// every suffix of the line, and a second built from the line reversed gives every prefix.
+fn force_line(chars: &[char], clues: &[u8]) -> Option<Vec<char>> {
+    let (n, k) = (chars.len(), clues.len());
+    let suffixes = CountTable::<Feasible>::new(chars, clues);
+    if !suffixes.total().0 {
+        return None;
+    }
+    let reversed_chars = chars.iter().rev().copied().collect::<Vec<_>>();
+    let reversed_clues = clues.iter().rev().copied().collect::<Vec<_>>();
+    let prefixes = CountTable::<Feasible>::new(&reversed_chars, &reversed_clues);
+    // Whether the first i cells fit the first j clues, and whether the cells from i on fit the rest
+    let prefix = |i: usize, j: usize| prefixes.count(i, j).0;
+    let suffix = |i: usize, j: usize| suffixes.count(n - i, k - j).0;
+
+    // A cell can be empty if the parts of the line either side of it fit between them
+    let can_empty = |i: usize| chars[i] != '#' && (0..=k).any(|j| prefix(i, j) && suffix(i + 1, j));
+
+    // A cell can be filled if it's under some placement of a block whose neighbours can be empty and
+    // whose surroundings still fit. Each placement adds one at its start and takes it off past its end.
+    let mut open_runs = vec![0; n + 1];
+    for i in 0..n {
+        open_runs[i + 1] = if chars[i] == '.' { 0 } else { open_runs[i] + 1 };
+    }
+    let mut coverage = vec![0i32; n + 1];
+    for (j, &len) in clues.iter().enumerate() {
+        let len = len as usize;
+        for start in 0..(n + 1).saturating_sub(len) {
+            let end = start + len;
+            let fits_before = if start == 0 { j == 0 } else { chars[start - 1] != '#' && prefix(start - 1, j) };
+            let fits_after = if end == n { j + 1 == k } else { chars[end] != '#' && suffix(end + 1, j + 1) };
+            if open_runs[end] >= len && fits_before && fits_after {
+                coverage[start] += 1;
+                coverage[end] -= 1;
+            }
+        }
+    }
+
+    let mut forced = chars.to_vec();
+    let mut covering = 0;
+    for i in 0..n {
+        covering += coverage[i];
+        if chars[i] != '?' {
+            continue;
+        }
+        if covering == 0 {
+            forced[i] = '.';
+        } else if !can_empty(i) {
+            forced[i] = '#';
+        }
+    }
+    Some(forced)
+}
+
+impl Nonogram {
+    pub fn new(rows: Vec<Vec<u8>>, cols: Vec<Vec<u8>>) -> Nonogram {
+        Nonogram { rows, cols }
+    }
+
+    pub fn width(&self) -> usize {
+        self.cols.len()
+    }
+
+    pub fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    fn lines(&self) -> impl Iterator<Item=Line> {
+        (0..self.height()).map(Line::Row).chain((0..self.width()).map(Line::Column))
+    }
+
+    fn clues(&self, line: Line) -> &[u8] {
+        match line {
+            Line::Row(row) => &self.rows[row],
+            Line::Column(col) => &self.cols[col],
+        }
+    }
+
+    fn cells(&self, grid: &Grid, line: Line) -> Vec<char> {
+        match line {
+            Line::Row(row) => grid[row].clone(),
+            Line::Column(col) => grid.iter().map(|row| row[col]).collect(),
+        }
+    }
+
+    fn set_cells(&self, grid: &mut Grid, line: Line, cells: Vec<char>) {
+        match line {
+            Line::Row(row) => grid[row] = cells,
+            Line::Column(col) => grid.iter_mut().zip(cells).for_each(|(row, c)| row[col] = c),
+        }
+    }
+
+    // Fills in every cell that is forced by its row or column, repeating until nothing changes
+    pub fn propagate(&self, grid: &mut Grid) -> Result<(), Proof> {
+        loop {
+            let mut changed = false;
+            for line in self.lines() {
+                let cells = self.cells(grid, line);
+                let forced = force_line(&cells, self.clues(line))
+                    .ok_or_else(|| Proof::Contradiction { line, cells: cells.iter().collect() })?;
+                if forced != cells {
+                    self.set_cells(grid, line, forced);
+                    changed = true;
+                }
+            }
+            if !changed {
+                return Ok(());
+            }
+        }
+    }
+
+    // Propagates, then guesses the first unknown cell both ways. Solutions below this grid are collected
+    // until there are limit of them, and if there are none the proof covers both guesses.
+    fn search(&self, mut grid: Grid, solutions: &mut Vec<Grid>, limit: usize) -> Result<(), Proof> {
+        self.propagate(&mut grid)?;
+        let unknown = grid.iter().enumerate()
+            .find_map(|(row, cells)| cells.iter().position(|&c| c == '?').map(|col| (row, col)));
+        let Some((row, col)) = unknown else {
+            solutions.push(grid);
+            return Ok(());
+        };
+        let guess = |c, solutions: &mut Vec<Grid>| {
+            let mut grid = grid.clone();
+            grid[row][col] = c;
+            self.search(grid, solutions, limit)
+        };
+        let filled = guess('#', solutions);
+        if solutions.len() >= limit {
+            return Ok(());
+        }
+        match (filled, guess('.', solutions)) {
+            (Err(filled), Err(empty)) => Err(Proof::Split { cell: (row, col), filled: Box::new(filled), empty: Box::new(empty) }),
+            _ => Ok(()),
+        }
+    }
+
+    fn search_from_empty(&self, limit: usize) -> Result<Vec<Grid>, Proof> {
+        let mut solutions = Vec::new();
+        self.search(vec![vec!['?'; self.width()]; self.height()], &mut solutions, limit)?;
+        Ok(solutions)
+    }
+
+    // Stops at the second solution, as that's enough to know it isn't unique
+    pub fn solve(&self) -> Outcome {
+        match self.search_from_empty(2) {
+            Err(proof) => Outcome::Unsolvable(proof),
+            Ok(mut solutions) if solutions.len() == 1 => Outcome::Unique(solutions.pop().unwrap()),
+            Ok(solutions) => Outcome::Multiple(solutions),
+        }
+    }
+
+    // Every solution, however many there are
+    pub fn solutions(&self) -> Vec<Grid> {
+        self.search_from_empty(usize::MAX).unwrap_or_default()
+    }
+}
+
+pub fn render(grid: &Grid) -> String {
+    grid.iter().map(|row| row.iter().collect::<String>() + "\n").collect()
+}
+
+// The row clues, a blank line, then the column clues. Each line is a comma separated list of run
+// lengths, like the day 12 records, with 0 for a line that is all empty.
+impl FromStr for Nonogram {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_clues = |block: &str| {
+            block.lines()
+                .map(|line| {
+                    line.trim().split(',')
+                        .map(|n| n.trim().parse::<u8>().map_err(|_| format!("Invalid clue '{}' in {}", n, line)))
+                        .filter(|n| *n != Ok(0))
+                        .collect::<Result<Vec<_>, _>>()
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let (rows, cols) = s.trim().split_once("\n\n").ok_or("Missing blank line before the column clues")?;
+        Ok(Nonogram::new(parse_clues(rows)?, parse_clues(cols)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::springs::Arrangements;
+
+    fn grid(s: &str) -> Grid {
+        s.lines().map(|line| line.chars().collect()).collect()
+    }
+
+    #[test]
+    fn unique_solution() {
+        let nonogram = "1\n1\n5\n1\n1\n\n1\n1\n5\n1\n1".parse::<Nonogram>().unwrap();
+        let solution = grid("..#..\n..#..\n#####\n..#..\n..#..");
+        assert_eq!(nonogram.solve(), Outcome::Unique(solution.clone()));
+        assert_eq!(render(&solution), "..#..\n..#..\n#####\n..#..\n..#..\n");
+    }
+
+    #[test]
+    fn propagation_alone() {
+        let nonogram = "3\n0\n1,1\n\n1,1\n1\n1,1".parse::<Nonogram>().unwrap();
+        let mut cells = vec![vec!['?'; 3]; 3];
+        nonogram.propagate(&mut cells).unwrap();
+        assert_eq!(cells, grid("###\n...\n#.#"));
+    }
+
+    #[test]
+    fn multiple_solutions() {
+        let nonogram = Nonogram::new(vec![vec![1], vec![1]], vec![vec![1], vec![1]]);
+        assert_eq!(nonogram.solve(), Outcome::Multiple(vec![grid("#.\n.#"), grid(".#\n#.")]));
+        assert_eq!(nonogram.solutions().len(), 2);
+
+        // A blank 8x8 grid with one cell per line has 8! solutions, but solving stops at two
+        let nonogram = Nonogram::new(vec![vec![1]; 8], vec![vec![1]; 8]);
+        let Outcome::Multiple(grids) = nonogram.solve() else {
+            panic!("Expected multiple solutions");
+        };
+        assert_eq!(grids.len(), 2);
+    }
+
+    #[test]
+    fn no_solution() {
+        let nonogram = Nonogram::new(vec![vec![2], vec![]], vec![vec![1], vec![]]);
+        assert_eq!(nonogram.solve(), Outcome::Unsolvable(Proof::Contradiction { line: Line::Column(1), cells: "#.".to_string() }));
+
+        // Every line has arrangements left after propagation, so the proof has to guess
+        let nonogram = "2\n2\n1\n1,1\n\n2\n1,1\n1,1\n1".parse::<Nonogram>().unwrap();
+        let Outcome::Unsolvable(proof) = nonogram.solve() else {
+            panic!("Expected no solution");
+        };
+        assert!(matches!(proof, Proof::Split { cell: (0, 0), .. }));
+        assert!(proof.to_string().starts_with("if row 1 column 1 is filled:\n  "));
+        assert!(nonogram.solutions().is_empty());
+    }
+
+    #[test]
+    fn force_line_matches_arrangements() {
+        // Every line of six cells, checked against listing out each arrangement
+        for code in 0..3usize.pow(6) {
+            let chars = (0..6).map(|i| ['#', '.', '?'][code / 3usize.pow(i) % 3]).collect::<Vec<_>>();
+            for clues in [&[][..], &[1], &[2], &[1, 1], &[3, 1], &[1, 1, 1]] {
+                let mut arrangements = Arrangements::new(&chars, clues);
+                let arrangements = arrangements.iter().map(|a| a.chars().collect::<Vec<_>>()).collect::<Vec<_>>();
+                let expected = (!arrangements.is_empty()).then(|| {
+                    (0..6).map(|i| {
+                        let c = arrangements[0][i];
+                        if arrangements.iter().all(|a| a[i] == c) { c } else { '?' }
+                    }).collect()
+                });
+                assert_eq!(force_line(&chars, clues), expected, "{:?} {:?}", chars, clues);
+            }
+        }
+    }
+
+    #[test]
+    fn force_wide_line() {
+        // Far more arrangements than fit in a u64, but nothing is forced
+        assert_eq!(force_line(&['?'; 200], &[1; 40]), Some(vec!['?'; 200]));
+
+        let mut forced = vec!['?'; 100];
+        forced[40..60].fill('#');
+        assert_eq!(force_line(&['?'; 100], &[60]), Some(forced));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!("1\n1".parse::<Nonogram>().is_err());
+        assert!("1\nx\n\n1".parse::<Nonogram>().is_err());
+    }
+}
@@ -0,0 +1,73 @@
+// Areas of simple polygons on the integer lattice. Vertices are given in order around the boundary, in
+// either direction, and the last vertex joins back to the first.
+pub type Vertex = (i64, i64);
+
+fn edges(vertices: &[Vertex]) -> impl Iterator<Item=(&Vertex, &Vertex)> {
+    vertices.iter().zip(vertices.iter().cycle().skip(1))
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// The shoelace formula, doubled so it stays an integer. Positive when the vertices run anticlockwise
+// with y pointing up (clockwise with y pointing down, as in a grid).
+pub fn twice_signed_area(vertices: &[Vertex]) -> i64 {
+    edges(vertices).map(|(a, b)| a.0 * b.1 - b.0 * a.1).sum()
+}
+
+pub fn area(vertices: &[Vertex]) -> f64 {
+    twice_signed_area(vertices).unsigned_abs() as f64 / 2.0
+}
+
+// The number of lattice points on the boundary, including the vertices
+pub fn boundary_points(vertices: &[Vertex]) -> u64 {
+    edges(vertices).map(|(a, b)| gcd(a.0.abs_diff(b.0), a.1.abs_diff(b.1))).sum()
+}
+
+// The number of lattice points strictly inside, from Pick's theorem: A = I + B/2 - 1. A polygon with no
+// area, such as one whose vertices are all in a line, has more boundary points than the theorem allows
+// for and nothing inside.
+pub fn interior_points(vertices: &[Vertex]) -> u64 {
+    (twice_signed_area(vertices).unsigned_abs() + 2).saturating_sub(boundary_points(vertices)) / 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn square() {
+        let square = [(0, 0), (4, 0), (4, 4), (0, 4)];
+        assert_eq!(twice_signed_area(&square), 32);
+        assert_eq!(area(&square), 16.0);
+        assert_eq!(boundary_points(&square), 16);
+        assert_eq!(interior_points(&square), 9);
+
+        // Reversing the direction flips the sign, and extra collinear vertices change nothing
+        assert_eq!(twice_signed_area(&[(0, 4), (4, 4), (4, 0), (0, 0)]), -32);
+        assert_eq!(interior_points(&[(0, 4), (4, 4), (4, 0), (0, 0)]), 9);
+        let with_midpoints = [(0, 0), (2, 0), (4, 0), (4, 4), (0, 4), (0, 1)];
+        assert_eq!(twice_signed_area(&with_midpoints), 32);
+        assert_eq!(interior_points(&with_midpoints), 9);
+    }
+
+    #[test]
+    fn triangle() {
+        let triangle = [(0, 0), (6, 0), (0, 3)];
+        assert_eq!(area(&triangle), 9.0);
+        assert_eq!(boundary_points(&triangle), 12);
+        assert_eq!(interior_points(&triangle), 4);
+        assert_eq!(interior_points(&[(0, 0), (1, 1)]), 0);
+
+        // Collinear vertices go out and back along the same edges, so the boundary is counted twice
+        let collinear = [(0, 0), (4, 0), (2, 0)];
+        assert_eq!(area(&collinear), 0.0);
+        assert_eq!(boundary_points(&collinear), 8);
+        assert_eq!(interior_points(&collinear), 0);
+    }
+}
@@ -0,0 +1,237 @@
+use std::iter;
+use std::ops::Add;
+
+// Anything arrangements can be counted in, such as usize, u128 or a BigUint for very long rows
+pub trait ArrangementCount: Clone + From<u8> + Add<Output=Self> {}
+
+impl<T: Clone + From<u8> + Add<Output=T>> ArrangementCount for T {}
+
+// Whether there are any arrangements at all, for when the exact count isn't needed and could overflow
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Feasible(pub bool);
+
+impl From<u8> for Feasible {
+    fn from(count: u8) -> Self {
+        Feasible(count > 0)
+    }
+}
+
+impl Add for Feasible {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Feasible(self.0 || other.0)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct SpringState<'a> {
+    chars: &'a [char],
+    damanged_lengths: &'a [u8],
+}
+
+impl<'a> SpringState<'a> {
+    pub fn new(chars: &'a [char], damanged_lengths: &'a [u8]) -> Self {
+        Self {
+            chars,
+            damanged_lengths,
+        }
+    }
+
+    pub fn count_possible_arrangements<T: ArrangementCount>(&self) -> T {
+        CountTable::new(self.chars, self.damanged_lengths).total()
+    }
+
+    fn consume_operational(&self) -> Option<Self> {
+        if self.chars.is_empty() || !(self.chars[0] == '.' || self.chars[0] == '?') {
+            return None;
+        }
+        Some(Self {
+            chars: &self.chars[1..],
+            damanged_lengths: self.damanged_lengths,
+        })
+    }
+
+    fn consume_damaged(&self) -> Option<Self> {
+        if self.chars.is_empty() || !(self.chars[0] == '#' || self.chars[0] == '?') || self.damanged_lengths.is_empty() {
+            return None;
+        }
+
+        let next_damaged_segments_count = self.damanged_lengths[0];
+
+        if self.chars.len() < next_damaged_segments_count as usize {
+            return None;
+        }
+        if self.chars[0..next_damaged_segments_count as usize].iter().any(|&c| !(c == '#' || c == '?')) {
+            return None;
+        }
+
+        // Character after damaged segment must be operational (. or ?), or end of string
+        match self.chars.get(next_damaged_segments_count as usize) {
+            Some('#') => None,
+            None => Some(Self {
+                chars: &self.chars[(next_damaged_segments_count as usize)..],
+                damanged_lengths: &self.damanged_lengths[1..],
+            }),
+            Some('.') | Some('?') => Some(Self {
+                chars: &self.chars[(next_damaged_segments_count as usize + 1)..],
+                damanged_lengths: &self.damanged_lengths[1..],
+            }),
+            Some(c) => panic!("Unexpected character {}", c),
+        }
+    }
+}
+
+// The number of arrangements of every suffix of a row, in one flat table indexed by how many chars
+// and damaged lengths are left
+pub struct CountTable<T> {
+    counts: Vec<T>,
+    width: usize,
+}
+
+impl<T: ArrangementCount> CountTable<T> {
+    pub fn new(chars: &[char], damanged_lengths: &[u8]) -> Self {
+        let width = damanged_lengths.len() + 1;
+        let mut table = Self { counts: Vec::with_capacity((chars.len() + 1) * width), width };
+        // Whether the last chars_left chars have no '#', so they're one arrangement once no lengths are left
+        let mut no_damaged_left = Vec::with_capacity(chars.len() + 1);
+        no_damaged_left.push(true);
+        for (chars_left, &c) in chars.iter().rev().enumerate() {
+            no_damaged_left.push(no_damaged_left[chars_left] && c != '#');
+        }
+        // Every move consumes at least one char, so filling from the shortest suffixes up means the
+        // states we move to are always already counted
+        for chars_left in 0..=chars.len() {
+            for lengths_left in 0..width {
+                let state = SpringState::new(
+                    &chars[chars.len() - chars_left..],
+                    &damanged_lengths[damanged_lengths.len() - lengths_left..],
+                );
+                let count = if lengths_left == 0 && no_damaged_left[chars_left] {
+                    T::from(1)
+                } else {
+                    let mut sum = T::from(0);
+                    if let Some(next_state) = state.consume_operational() {
+                        sum = sum + table.get(&next_state);
+                    }
+                    if let Some(next_state) = state.consume_damaged() {
+                        sum = sum + table.get(&next_state);
+                    }
+                    sum
+                };
+                table.counts.push(count);
+            }
+        }
+        table
+    }
+
+    // The count for a state partway along the row the table was built from
+    fn get(&self, state: &SpringState) -> T {
+        self.count(state.chars.len(), state.damanged_lengths.len())
+    }
+
+    // The count for the last chars_left chars of the row with the last lengths_left damaged lengths
+    pub fn count(&self, chars_left: usize, lengths_left: usize) -> T {
+        self.counts[chars_left * self.width + lengths_left].clone()
+    }
+
+    pub fn total(&self) -> T {
+        self.counts.last().unwrap().clone()
+    }
+}
+
+// The valid arrangements of a row, ordered lexicographically ('#' before '.'). The count from every
+// state is cached, so any single arrangement can be picked out without building the ones before it.
+pub struct Arrangements<'a> {
+    chars: &'a [char],
+    damaged_lengths: &'a [u8],
+    counts: CountTable<usize>,
+    total: usize,
+}
+
+impl<'a> Arrangements<'a> {
+    pub fn new(chars: &'a [char], damaged_lengths: &'a [u8]) -> Self {
+        let counts = CountTable::new(chars, damaged_lengths);
+        let total = counts.total();
+        Self { chars, damaged_lengths, counts, total }
+    }
+
+    pub fn len(&self) -> usize {
+        self.total
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total == 0
+    }
+
+    // The kth arrangement, choosing damaged before operational at each step and skipping over the
+    // whole subtree of arrangements on the damaged side when k is beyond it
+    pub fn nth(&mut self, mut k: usize) -> Option<String> {
+        if k >= self.total {
+            return None;
+        }
+        let mut state = SpringState::new(self.chars, self.damaged_lengths);
+        let mut arrangement = String::with_capacity(self.chars.len());
+        // Once every damaged length is placed, the rest can only be operational
+        while !state.damanged_lengths.is_empty() {
+            if let Some(next_state) = state.consume_damaged() {
+                let count = self.counts.get(&next_state);
+                if k < count {
+                    let damaged_length = state.damanged_lengths[0] as usize;
+                    arrangement.extend(iter::repeat_n('#', damaged_length));
+                    arrangement.extend(iter::repeat_n('.', state.chars.len() - damaged_length - next_state.chars.len()));
+                    state = next_state;
+                    continue;
+                }
+                k -= count;
+            }
+            state = state.consume_operational().unwrap();
+            arrangement.push('.');
+        }
+        arrangement.extend(iter::repeat_n('.', state.chars.len()));
+        Some(arrangement)
+    }
+
+    // Every arrangement in order, built one at a time as the iterator is advanced
+    pub fn iter(&mut self) -> impl Iterator<Item=String> + use<'_, 'a> {
+        (0..self.total).map(|k| self.nth(k).unwrap())
+    }
+
+    pub fn sample(&mut self, rng: &mut Rng) -> Option<String> {
+        if self.is_empty() {
+            return None;
+        }
+        let k = rng.below(self.total as u64) as usize;
+        self.nth(k)
+    }
+}
+
+// A small xorshift* generator, good enough for picking arrangements
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // Xorshift gets stuck at zero, so mix the seed into a nonzero state
+        Self { state: seed ^ 0x9E37_79B9_7F4A_7C15 | 1 }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    // A uniform number below n, rejecting the top partial block of outputs to avoid modulo bias
+    pub fn below(&mut self, n: u64) -> u64 {
+        let limit = u64::MAX - u64::MAX % n;
+        loop {
+            let x = self.next_u64();
+            if x < limit {
+                return x % n;
+            }
+        }
+    }
+}